@@ -0,0 +1,53 @@
+//! Sequential versus rayon timing for the per-input work `--jobs` spreads
+//! across threads: parsing a spec and rendering its pretty block. Run with
+//! `cargo bench --bench jobs`; the speedup tracks the number of cores.
+
+use cidr::{RenderOpts, describe_string, parse_network};
+use rayon::prelude::*;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+const INPUTS: u32 = 200_000;
+
+fn timed<T>(work: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let out = work();
+    (out, start.elapsed())
+}
+
+fn main() {
+    colored::control::set_override(false);
+    let specs: Vec<String> = (0..INPUTS)
+        .map(|i| {
+            format!(
+                "{}/{}",
+                Ipv4Addr::from(i.wrapping_mul(2_654_435_761)),
+                8 + i % 23
+            )
+        })
+        .collect();
+    let opts = RenderOpts::default();
+    let render = |spec: &String| {
+        let net = parse_network(spec, None).expect("generated spec parses");
+        describe_string(&net, &opts)
+    };
+
+    let (sequential, base) = timed(|| specs.iter().map(render).collect::<Vec<_>>());
+    println!("sequential   {:>8.1?}", base);
+
+    for jobs in [2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .expect("thread pool");
+        let (parallel, took) =
+            timed(|| pool.install(|| specs.par_iter().map(render).collect::<Vec<_>>()));
+        assert_eq!(parallel, sequential, "--jobs must keep input order");
+        println!(
+            "--jobs {:<4}  {:>8.1?}  {:.2}x",
+            jobs,
+            took,
+            base.as_secs_f64() / took.as_secs_f64()
+        );
+    }
+}
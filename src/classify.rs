@@ -0,0 +1,235 @@
+//! Address-scope classification for IPv4 blocks.
+
+use crate::ops::{contains, overlaps};
+use crate::parse_network;
+use eyre::{Result, WrapErr, eyre};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// Broad usage category of an IPv4 block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Private,
+    Loopback,
+    LinkLocal,
+    Shared,
+    Multicast,
+    Public,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Scope::Private => "Private (RFC1918)",
+            Scope::Loopback => "Loopback",
+            Scope::LinkLocal => "Link-Local",
+            Scope::Shared => "Shared (CGNAT)",
+            Scope::Multicast => "Multicast",
+            Scope::Public => "Public",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Well-known non-public ranges, checked in order.
+const SCOPES: [([u8; 4], u8, Scope); 7] = [
+    ([10, 0, 0, 0], 8, Scope::Private),
+    ([172, 16, 0, 0], 12, Scope::Private),
+    ([192, 168, 0, 0], 16, Scope::Private),
+    ([127, 0, 0, 0], 8, Scope::Loopback),
+    ([169, 254, 0, 0], 16, Scope::LinkLocal),
+    ([100, 64, 0, 0], 10, Scope::Shared),
+    ([224, 0, 0, 0], 4, Scope::Multicast),
+];
+
+/// Classify a block by the first well-known range it touches, or `Public`.
+pub fn classify(net: &Ipv4Network) -> Scope {
+    first_match(net).map_or(Scope::Public, |(scope, _)| scope)
+}
+
+/// Whether the block straddles its classified range and another category.
+pub fn mixed_scope(net: &Ipv4Network) -> bool {
+    match first_match(net) {
+        Some((_, range)) => !contains(&range, net),
+        None => false,
+    }
+}
+
+fn first_match(net: &Ipv4Network) -> Option<(Scope, Ipv4Network)> {
+    SCOPES.iter().find_map(|&(octets, prefix, scope)| {
+        let range = Ipv4Network::new(Ipv4Addr::from(octets), prefix).expect("valid scope range");
+        overlaps(&range, net).then_some((scope, range))
+    })
+}
+
+/// Build a table entry at compile time.
+const fn block(octets: [u8; 4], prefix: u8) -> Ipv4Network {
+    match Ipv4Network::new_checked(
+        Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]),
+        prefix,
+    ) {
+        Some(net) => net,
+        None => panic!("invalid special-use prefix"),
+    }
+}
+
+/// The IANA IPv4 Special-Purpose Address Registry (RFC 6890 and successors).
+const SPECIAL_USE: [(Ipv4Network, &str); 25] = [
+    (block([0, 0, 0, 0], 8), "\"This network\" (RFC 791)"),
+    (
+        block([0, 0, 0, 0], 32),
+        "\"This host on this network\" (RFC 1122)",
+    ),
+    (block([10, 0, 0, 0], 8), "Private-Use (RFC 1918)"),
+    (
+        block([100, 64, 0, 0], 10),
+        "Shared Address Space (RFC 6598)",
+    ),
+    (block([127, 0, 0, 0], 8), "Loopback (RFC 1122)"),
+    (block([169, 254, 0, 0], 16), "Link Local (RFC 3927)"),
+    (block([172, 16, 0, 0], 12), "Private-Use (RFC 1918)"),
+    (
+        block([192, 0, 0, 0], 24),
+        "IETF Protocol Assignments (RFC 6890)",
+    ),
+    (
+        block([192, 0, 0, 0], 29),
+        "IPv4 Service Continuity Prefix (RFC 7335)",
+    ),
+    (block([192, 0, 0, 8], 32), "IPv4 dummy address (RFC 7600)"),
+    (
+        block([192, 0, 0, 9], 32),
+        "Port Control Protocol Anycast (RFC 7723)",
+    ),
+    (
+        block([192, 0, 0, 10], 32),
+        "Traversal Using Relays around NAT Anycast (RFC 8155)",
+    ),
+    (
+        block([192, 0, 0, 170], 32),
+        "NAT64/DNS64 Discovery (RFC 7050)",
+    ),
+    (
+        block([192, 0, 0, 171], 32),
+        "NAT64/DNS64 Discovery (RFC 7050)",
+    ),
+    (
+        block([192, 0, 2, 0], 24),
+        "Documentation, TEST-NET-1 (RFC 5737)",
+    ),
+    (block([192, 31, 196, 0], 24), "AS112-v4 (RFC 7535)"),
+    (block([192, 52, 193, 0], 24), "AMT (RFC 7450)"),
+    (
+        block([192, 88, 99, 0], 24),
+        "Deprecated 6to4 Relay Anycast (RFC 7526)",
+    ),
+    (block([192, 168, 0, 0], 16), "Private-Use (RFC 1918)"),
+    (
+        block([192, 175, 48, 0], 24),
+        "Direct Delegation AS112 Service (RFC 7534)",
+    ),
+    (block([198, 18, 0, 0], 15), "Benchmarking (RFC 2544)"),
+    (
+        block([198, 51, 100, 0], 24),
+        "Documentation, TEST-NET-2 (RFC 5737)",
+    ),
+    (
+        block([203, 0, 113, 0], 24),
+        "Documentation, TEST-NET-3 (RFC 5737)",
+    ),
+    (block([240, 0, 0, 0], 4), "Reserved (RFC 1112)"),
+    (
+        block([255, 255, 255, 255], 32),
+        "Limited Broadcast (RFC 919)",
+    ),
+];
+
+/// The most specific IANA special-purpose block containing `net`, if any;
+/// nested entries such as 192.0.0.8/32 win over their enclosing block.
+pub fn special_use(net: &Ipv4Network) -> Option<&'static str> {
+    SPECIAL_USE
+        .iter()
+        .filter(|(block, _)| contains(block, net))
+        .max_by_key(|(block, _)| block.prefix())
+        .map(|&(_, name)| name)
+}
+
+/// Parse a user classification file of `name = "cidr"` pairs, e.g.
+/// `"dmz" = "10.0.5.0/24"`, into the table `user_label` searches.
+///
+/// # Errors
+/// Returns an error if the text is not TOML, or a value is not an IPv4
+/// network string, naming the key.
+pub fn parse_labels(text: &str) -> Result<Vec<(Ipv4Network, String)>> {
+    let table: toml::Table = text.parse().wrap_err("Invalid labels file")?;
+    table
+        .into_iter()
+        .map(|(name, value)| {
+            let spec = value
+                .as_str()
+                .ok_or_else(|| eyre!("label '{}' must be a network string", name))?;
+            match parse_network(spec, None).wrap_err_with(|| format!("label '{}'", name))? {
+                IpNetwork::V4(net) => Ok((net, name)),
+                IpNetwork::V6(net) => Err(eyre!("label '{}': {} is not IPv4", name, net)),
+            }
+        })
+        .collect()
+}
+
+/// The user's name for `net`: the most specific labeled block containing it.
+pub fn user_label<'a>(net: &Ipv4Network, labels: &'a [(Ipv4Network, String)]) -> Option<&'a str> {
+    labels
+        .iter()
+        .filter(|(block, _)| contains(block, net))
+        .max_by_key(|(block, _)| block.prefix())
+        .map(|(_, name)| name.as_str())
+}
+
+/// Legacy classful category of an IPv4 address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpClass {
+    A,
+    B,
+    C,
+    D,
+    E,
+}
+
+impl IpClass {
+    /// Implied classful prefix; classes D and E have none.
+    pub fn default_prefix(self) -> Option<u8> {
+        match self {
+            IpClass::A => Some(8),
+            IpClass::B => Some(16),
+            IpClass::C => Some(24),
+            IpClass::D | IpClass::E => None,
+        }
+    }
+}
+
+impl fmt::Display for IpClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Subnet bits borrowed from the classful default mask: a /26 in class C
+/// (default /24) borrows 2. Negative when the block is wider than its
+/// class default (a supernet); `None` for classes D and E.
+pub fn borrowed_bits(net: &Ipv4Network) -> Option<i16> {
+    ip_class(net.network())
+        .default_prefix()
+        .map(|default| i16::from(net.prefix()) - i16::from(default))
+}
+
+/// Classify an address by its leading bits, as pre-CIDR routing did.
+pub fn ip_class(addr: Ipv4Addr) -> IpClass {
+    match addr.octets()[0] {
+        0..=127 => IpClass::A,
+        128..=191 => IpClass::B,
+        192..=223 => IpClass::C,
+        224..=239 => IpClass::D,
+        _ => IpClass::E,
+    }
+}
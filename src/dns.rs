@@ -0,0 +1,55 @@
+//! Reverse-DNS zone naming for IPv4 blocks.
+
+use ipnetwork::Ipv4Network;
+
+/// `in-addr.arpa` zones that delegate the block.
+///
+/// Prefixes up to `/24` list every `/24` zone spanned; longer prefixes use
+/// RFC 2317 classless naming such as `0/25.2.0.192.in-addr.arpa`, and a `/32`
+/// yields the single PTR owner name.
+///
+/// Names are built lazily, so a caller can cap a `/0` (16,777,216 zones)
+/// without allocating them all; `len` is known up front.
+pub fn ptr_zones(net: &Ipv4Network) -> impl ExactSizeIterator<Item = String> + use<> {
+    let prefix = net.prefix();
+    let [a, b, c, d] = net.network().octets();
+    let (first, count) = match prefix {
+        25..=32 => (0, 1),
+        _ => (u32::from(net.network()) >> 8, 1u32 << (24 - prefix)),
+    };
+    (first..first + count).map(move |zone| match prefix {
+        32 => format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a),
+        25..=31 => format!("{}/{}.{}.{}.{}.in-addr.arpa", d, prefix, c, b, a),
+        _ => {
+            let [_, a, b, c] = zone.to_be_bytes();
+            format!("{}.{}.{}.in-addr.arpa", c, b, a)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ptr_zones_counts_without_building() {
+        let all: Ipv4Network = "0.0.0.0/0".parse().unwrap();
+        let mut zones = ptr_zones(&all);
+        assert_eq!(zones.len(), 1 << 24);
+        assert_eq!(zones.next().as_deref(), Some("0.0.0.in-addr.arpa"));
+    }
+
+    #[test]
+    fn ptr_zones_classless_and_host() {
+        let sub: Ipv4Network = "192.0.2.128/25".parse().unwrap();
+        let host: Ipv4Network = "192.0.2.5/32".parse().unwrap();
+        assert_eq!(
+            ptr_zones(&sub).collect::<Vec<_>>(),
+            ["128/25.2.0.192.in-addr.arpa"]
+        );
+        assert_eq!(
+            ptr_zones(&host).collect::<Vec<_>>(),
+            ["5.2.0.192.in-addr.arpa"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! IPv6 forms derived from IPv4 addresses for dual-stack transition, and
+//! IPv6-only calculations with no IPv4 counterpart.
+
+use eyre::{Result, eyre};
+use ipnetwork::Ipv6Network;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The IPv4-mapped IPv6 address (`::ffff:a.b.c.d`, RFC 4291).
+pub fn v4_mapped(addr: Ipv4Addr) -> Ipv6Addr {
+    addr.to_ipv6_mapped()
+}
+
+/// The 6to4 `/48` embedding the address after `2002::/16` (RFC 3056).
+pub fn sixtofour_prefix(addr: Ipv4Addr) -> Ipv6Network {
+    let [a, b, c, d] = addr.octets();
+    let ip = Ipv6Addr::new(
+        0x2002,
+        u16::from_be_bytes([a, b]),
+        u16::from_be_bytes([c, d]),
+        0,
+        0,
+        0,
+        0,
+        0,
+    );
+    Ipv6Network::new(ip, 48).expect("48 is a valid IPv6 prefix")
+}
+
+/// How many `/64` subnets fit in the block; `None` for prefixes longer than 64.
+pub fn subnets_64(net: &Ipv6Network) -> Option<u128> {
+    (net.prefix() <= 64).then(|| 1u128 << (64 - net.prefix()))
+}
+
+/// The solicited-node multicast group for an address: `ff02::1:ff00:0/104`
+/// plus its low 24 bits (RFC 4291 §2.7.1), as joined for NDP.
+pub fn solicited_node(addr: Ipv6Addr) -> Ipv6Addr {
+    let base = u128::from(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0));
+    Ipv6Addr::from(base | (u128::from(addr) & 0x00ff_ffff))
+}
+
+/// The SLAAC address for `mac` in `prefix`: the prefix's upper 64 bits and
+/// the modified EUI-64 interface identifier, which flips the MAC's
+/// universal/local bit and inserts `ff:fe` in the middle (RFC 4291 App. A).
+pub fn eui64(prefix: Ipv6Network, mac: [u8; 6]) -> Ipv6Addr {
+    let [a, b, c, d, e, f] = mac;
+    let iid = u64::from_be_bytes([a ^ 0x02, b, c, 0xff, 0xfe, d, e, f]);
+    let upper = u128::from(prefix.network()) & !u128::from(u64::MAX);
+    Ipv6Addr::from(upper | u128::from(iid))
+}
+
+/// Parse a MAC address written `00:11:22:33:44:55`, `00-11-22-33-44-55`
+/// or Cisco-style `0011.2233.4455`.
+///
+/// # Errors
+/// Returns an error unless the text holds exactly six hex octets in one of
+/// those layouts.
+pub fn parse_mac(text: &str) -> Result<[u8; 6]> {
+    let invalid = || eyre!("'{}' is not a MAC address like 00:11:22:33:44:55", text);
+    let digits: String = if text.contains('.') {
+        let groups: Vec<&str> = text.split('.').collect();
+        if groups.len() != 3 || groups.iter().any(|g| g.len() != 4) {
+            return Err(invalid());
+        }
+        groups.concat()
+    } else {
+        let groups: Vec<&str> = text.split([':', '-']).collect();
+        if groups.len() != 6 || groups.iter().any(|g| g.len() != 2) {
+            return Err(invalid());
+        }
+        groups.concat()
+    };
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut mac = [0u8; 6];
+    for (i, octet) in mac.iter_mut().enumerate() {
+        *octet = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).expect("validated hex digits");
+    }
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v6(text: &str) -> Ipv6Addr {
+        text.parse().expect("valid test address")
+    }
+
+    #[test]
+    fn solicited_node_known_examples() {
+        assert_eq!(solicited_node(v6("2001:db8::1")), v6("ff02::1:ff00:1"));
+        assert_eq!(
+            solicited_node(v6("fe80::2aa:ff:fe28:9c5a")),
+            v6("ff02::1:ff28:9c5a")
+        );
+        assert_eq!(
+            solicited_node(v6("2001:db8:1:2:abcd:ef12:3456:789a")),
+            v6("ff02::1:ff56:789a")
+        );
+    }
+
+    #[test]
+    fn eui64_known_mac_and_prefix() {
+        let link_local: Ipv6Network = "fe80::/64".parse().unwrap();
+        let mac = parse_mac("00:aa:00:3f:2a:1c").unwrap();
+        assert_eq!(eui64(link_local, mac), v6("fe80::2aa:ff:fe3f:2a1c"));
+
+        let global: Ipv6Network = "2001:db8:1:2::/64".parse().unwrap();
+        let mac = parse_mac("000c.290c.47d5").unwrap();
+        assert_eq!(eui64(global, mac), v6("2001:db8:1:2:20c:29ff:fe0c:47d5"));
+    }
+}
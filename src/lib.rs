@@ -0,0 +1,839 @@
+//! CIDR parsing and network calculations shared by the `cidr` binary.
+
+use eyre::{Result, WrapErr, eyre};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use lazy_static::lazy_static;
+use serde::{Serialize, Serializer};
+use std::env;
+use std::io::BufRead;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+mod classify;
+mod dns;
+mod ipv6;
+mod ops;
+mod render;
+
+pub use classify::{
+    IpClass, Scope, borrowed_bits, classify, ip_class, mixed_scope, parse_labels, special_use,
+    user_label,
+};
+pub use dns::ptr_zones;
+pub use ipv6::{eui64, parse_mac, sixtofour_prefix, solicited_node, subnets_64, v4_mapped};
+pub use ops::{
+    Gateway, HostRole, PlanDiff, Relationship, aggregate, aggregate_lines, are_siblings,
+    block_at_prefix, bucket, contains, diff, exclude, exclude_all, gateway, host_range, host_role,
+    hosts, intersect, longest_match, next_network, nth_host, overlapping_pairs, overlaps,
+    prev_network, range_to_cidrs, relationship, route_prefix, smallest_covering, sort_networks,
+    supernet, usable_hosts, vlsm,
+};
+pub use render::{MaskStyle, RenderOpts, anonymize, describe_string, format_count, hex};
+
+lazy_static! {
+    /// Base IP for any leading "/prefix" argument when no `--base` is given:
+    /// If $CIDR_DEFAULT_IP (or the older $DEFAULT_NETWORK) is set and valid, we
+    /// parse just the IP portion (dropping any trailing "/24" etc.); otherwise
+    /// we fall back to "192.168.1.1".
+    pub static ref DEFAULT_NETWORK: IpAddr = {
+        // grab the raw, or fallback literal
+        let (var, raw) = ["CIDR_DEFAULT_IP", "DEFAULT_NETWORK"]
+            .into_iter()
+            .find_map(|var| env::var(var).ok().map(|raw| (var, raw)))
+            .unwrap_or(("", "192.168.1.1".into()));
+        // drop any "/…" suffix
+        let ip_part = raw.split('/').next().unwrap();
+        // parse or warn+fallback
+        ip_part.parse().unwrap_or_else(|_| {
+            eprintln!(
+                "warning: env ${} ('{}') invalid; using 192.168.1.1",
+                var, raw
+            );
+            "192.168.1.1".parse().unwrap()
+        })
+    };
+}
+
+/// Computed fields for a single network block.
+///
+/// IPv6 blocks have no broadcast, so `broadcast` is `None` and every address
+/// counts as usable, as do both ends of an IPv4 `/31` (RFC 3021). Blocks
+/// without a host range report `None` for the host fields so serialized
+/// output keeps a consistent schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkInfo {
+    pub network: IpAddr,
+    pub broadcast: Option<IpAddr>,
+    pub netmask: IpAddr,
+    pub prefix: u8,
+    pub first_host: Option<IpAddr>,
+    pub last_host: Option<IpAddr>,
+    #[serde(serialize_with = "serialize_usable")]
+    pub usable: Option<u128>,
+    /// Every address in the block, network and broadcast included: 2^32 for
+    /// `0.0.0.0/0` and 1 for a `/32`. Counts are `u128` throughout so the
+    /// IPv4 `/0` and `/1` never overflow; only the IPv6 `::/0`, whose 2^128
+    /// addresses cannot be represented, saturates at `u128::MAX`, which
+    /// serializes as the string `"2^128"` (see `count_text`).
+    #[serde(serialize_with = "serialize_count")]
+    pub total: u128,
+    /// The network address as an unsigned integer.
+    pub network_int: u128,
+    /// The broadcast address as an unsigned integer, when there is one.
+    pub broadcast_int: Option<u128>,
+}
+
+/// Expand a mix of full “IP/prefix” and “/prefix” args into all full specs,
+/// giving any “/prefix” before the first full spec the `base` IP (usually
+/// `DEFAULT_NETWORK`); later ones inherit the last IP seen. Arguments holding a
+/// comma- or whitespace-separated list are split into one spec per item.
+///
+/// Tokens whose IP fails to parse pass through unchanged (and do not become
+/// the inherited IP) so `parse_network` can report them individually.
+///
+/// A dashed IPv4 range such as `10.0.0.5-10.0.0.20` expands to the minimal
+/// covering CIDRs, exactly as `--range` would. Both sides must be dotted or
+/// `0x`-hex addresses, so a bare `-`, a negative-looking `-5` or `10-20` is
+/// never a range.
+///
+/// A trailing `# comment` on an argument is dropped before splitting; use
+/// `expand_labeled_with` to keep it.
+///
+/// # Errors
+/// Returns an error if a dashed range starts after it ends.
+pub fn expand_args(raw_args: &[String], base: IpAddr) -> Result<Vec<String>> {
+    let mut last_ip = base;
+    expand_args_with(raw_args, &mut last_ip)
+}
+
+/// `expand_args` continuing from, and updating, a caller-held last IP, so
+/// `/prefix` keeps inheriting across separate calls (as in the REPL).
+///
+/// # Errors
+/// Returns an error if a dashed range starts after it ends.
+pub fn expand_args_with(raw_args: &[String], last_ip: &mut IpAddr) -> Result<Vec<String>> {
+    let labeled = expand_labeled_with(raw_args, last_ip)?;
+    Ok(labeled.into_iter().map(|(spec, _)| spec).collect())
+}
+
+/// `expand_args_with`, pairing each spec with the trailing `# comment` of the
+/// argument it came from, e.g. `10.0.0.0/24   # prod web` gives
+/// `("10.0.0.0/24", Some("prod web"))`.
+///
+/// # Errors
+/// Returns an error if a dashed range starts after it ends.
+pub fn expand_labeled_with(
+    raw_args: &[String],
+    last_ip: &mut IpAddr,
+) -> Result<Vec<(String, Option<String>)>> {
+    let mut out = Vec::with_capacity(raw_args.len());
+
+    let tokens = raw_args.iter().flat_map(|arg| {
+        let (body, comment) = split_comment(arg);
+        body.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tok| !tok.is_empty())
+            .map(move |tok| (tok, comment))
+    });
+
+    for (raw, comment) in tokens {
+        let comment = comment.map(str::to_string);
+        if let Some((start, end)) = dash_range(raw) {
+            let blocks =
+                range_to_cidrs(start, end).wrap_err_with(|| format!("Invalid range '{}'", raw))?;
+            out.extend(blocks.iter().map(|b| (b.to_string(), comment.clone())));
+            continue;
+        }
+        let spec = if let Some(tok) = raw.strip_prefix('/') {
+            if tok.contains('/') {
+                let mut parts = tok.splitn(2, '/');
+                let ip_str = parts.next().unwrap();
+                if let Ok(ip) = parse_ip(ip_str) {
+                    *last_ip = ip;
+                }
+                tok.to_string()
+            } else {
+                format!("{}/{}", last_ip, tok)
+            }
+        } else {
+            let mut parts = raw.splitn(2, '/');
+            let ip_str = parts.next().unwrap();
+            if let Ok(ip) = parse_ip(ip_str) {
+                *last_ip = ip;
+            }
+            raw.to_string()
+        };
+
+        out.push((spec, comment));
+    }
+
+    Ok(out)
+}
+
+/// Split off a trailing `# comment`, trimming whitespace around both parts.
+/// An empty comment counts as none.
+pub fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.split_once('#') {
+        Some((body, comment)) => {
+            let comment = comment.trim();
+            (body.trim(), (!comment.is_empty()).then_some(comment))
+        }
+        None => (line.trim(), None),
+    }
+}
+
+/// Split a `start-end` token into its two IPv4 endpoints, if it is one.
+/// Each side must be dotted or `0x`-hex: bare integers would make `10-20`
+/// a range.
+fn dash_range(tok: &str) -> Option<(Ipv4Addr, Ipv4Addr)> {
+    let (start, end) = tok.split_once('-')?;
+    let bare = |side: &str| side.bytes().all(|b| b.is_ascii_digit());
+    if bare(start) || bare(end) {
+        return None;
+    }
+    Some((parse_addr(start).ok()?, parse_addr(end).ok()?))
+}
+
+/// Read newline-separated specs, skipping blank lines and `#` comments.
+///
+/// # Errors
+/// Returns an error if the reader fails.
+pub fn read_specs<R: BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    for line in reader.lines() {
+        let line = line.wrap_err("Failed to read input")?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        out.push(trimmed.to_string());
+    }
+    Ok(out)
+}
+
+/// Parse an IPv4 address written as hex (`0xC0A80101`), a 32-bit integer
+/// (`3232235777`), or dotted-decimal, trying those forms in order.
+///
+/// # Errors
+/// Returns an error naming every form attempted if none match.
+pub fn parse_addr(tok: &str) -> Result<Ipv4Addr> {
+    let parsed = if let Some(hex) = tok.strip_prefix("0x").or_else(|| tok.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok().map(Ipv4Addr::from)
+    } else if !tok.is_empty() && tok.bytes().all(|b| b.is_ascii_digit()) {
+        tok.parse::<u32>().ok().map(Ipv4Addr::from)
+    } else {
+        Ipv4Addr::from_str(tok).ok()
+    };
+    parsed.ok_or_else(|| match unpadded(tok) {
+        Some(plain) => eyre!(
+            "'{}' has zero-padded octets, which some tools read as octal; write {} \
+             or pass --lenient to read them as decimal",
+            tok,
+            plain
+        ),
+        None => eyre!(
+            "'{}' is not an IPv4 address (tried 0x-hex, 32-bit integer, and dotted-decimal)",
+            tok
+        ),
+    })
+}
+
+/// `010.001.000.001` as `10.1.0.1` when the text is a dotted quad with at
+/// least one zero-padded octet; `None` otherwise.
+fn unpadded(tok: &str) -> Option<String> {
+    let octets: Vec<&str> = tok.split('.').collect();
+    let quad = octets.len() == 4
+        && octets
+            .iter()
+            .all(|o| !o.is_empty() && o.bytes().all(|b| b.is_ascii_digit()));
+    if !quad || !octets.iter().any(|o| o.len() > 1 && o.starts_with('0')) {
+        return None;
+    }
+    let plain: Vec<&str> = octets
+        .iter()
+        .map(|o| match o.trim_start_matches('0') {
+            "" => "0",
+            digits => digits,
+        })
+        .collect();
+    Some(plain.join("."))
+}
+
+/// Rewrite every zero-padded dotted quad in `text` as plain decimal
+/// (`010.0.0.001/24` becomes `10.0.0.1/24`), leaving everything else,
+/// including prefixes, ranges and comments, as written.
+pub fn strip_leading_zeros(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    for c in text.chars().chain(std::iter::once('\0')) {
+        if c.is_ascii_digit() || c == '.' {
+            run.push(c);
+            continue;
+        }
+        out.push_str(&unpadded(&run).unwrap_or_else(|| run.clone()));
+        run.clear();
+        if c != '\0' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Parse an IPv6 address, or an IPv4 address in any form `parse_addr` accepts.
+fn parse_ip(tok: &str) -> Result<IpAddr> {
+    if tok.contains(':') {
+        let v6 = Ipv6Addr::from_str(tok)
+            .wrap_err_with(|| format!("'{}' is not an IPv6 address", tok))?;
+        Ok(IpAddr::V6(v6))
+    } else {
+        parse_addr(tok).map(IpAddr::V4)
+    }
+}
+
+/// Parse either “addr/prefix” or “addr” + separate netmask into an `IpNetwork`,
+/// dispatching on whether the address is IPv4 or IPv6.
+///
+/// # Errors
+/// Returns an error if either part fails to parse, or if `address` already
+/// carries a `/prefix` while a mask is also given.
+pub fn parse_network(address: &str, mask: Option<&str>) -> Result<IpNetwork> {
+    if let Some(mask_str) = mask {
+        let ip = bare_ip(address, "--mask")?;
+        let mask_ip = parse_mask(mask_str).wrap_err("Invalid network mask")?;
+        let prefix = match (ip, mask_ip) {
+            (IpAddr::V4(_), IpAddr::V4(m)) => mask_to_prefix(m)?,
+            (IpAddr::V6(_), IpAddr::V6(m)) => v6_mask_to_prefix(m)?,
+            _ => {
+                return Err(eyre!(
+                    "Network mask family does not match address '{}'",
+                    address
+                ));
+            }
+        };
+        IpNetwork::new(ip, prefix).wrap_err("Failed to build network from mask")
+    } else {
+        let (addr, prefix) = match address.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (address, None),
+        };
+        let ip = parse_ip(addr).wrap_err("Invalid address/prefix format")?;
+        let prefix = match prefix {
+            Some(p) => parse_prefix(p, ip.is_ipv4())?,
+            None if ip.is_ipv4() => 32,
+            None => 128,
+        };
+        IpNetwork::new(ip, prefix).wrap_err("Invalid address/prefix format")
+    }
+}
+
+/// A netmask in dotted or IPv6 form, or as a `0x`-prefixed 32-bit hex
+/// value such as `0xffffff00` as found in firmware configs.
+fn parse_mask(mask: &str) -> Result<IpAddr> {
+    match mask.strip_prefix("0x").or_else(|| mask.strip_prefix("0X")) {
+        Some(digits) => {
+            if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(eyre!("'{}' is not a hex mask", mask));
+            }
+            if digits.is_empty() || digits.len() > 8 {
+                return Err(eyre!(
+                    "hex mask '{}' must have 1 to 8 hex digits after 0x",
+                    mask
+                ));
+            }
+            let bits = u32::from_str_radix(digits, 16).expect("validated hex digits");
+            Ok(IpAddr::V4(Ipv4Addr::from(bits)))
+        }
+        None => Ok(mask.parse()?),
+    }
+}
+
+/// Prefix length of a contiguous netmask (all ones, then all zeros).
+///
+/// # Errors
+/// Returns an error for a non-contiguous mask such as `255.0.255.0`.
+pub fn mask_to_prefix(mask: Ipv4Addr) -> Result<u8> {
+    let m = u32::from(mask);
+    // Inverting a contiguous mask leaves only low ones; adding one carries
+    // them into a single bit (or to zero for /0).
+    let carry = (!m).wrapping_add(1);
+    if carry != 0 && !carry.is_power_of_two() {
+        return Err(eyre!("invalid non-contiguous mask {}", mask));
+    }
+    Ok(m.leading_ones() as u8)
+}
+
+/// Netmask with the top `prefix` bits set, the inverse of `mask_to_prefix`.
+///
+/// # Errors
+/// Returns an error if `prefix` is greater than 32.
+pub fn prefix_to_mask(prefix: u8) -> Result<Ipv4Addr> {
+    check_prefix(u64::from(prefix), true)?;
+    let bits = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+    Ok(Ipv4Addr::from(bits))
+}
+
+/// Prefix length of a contiguous Cisco-style wildcard mask (all zeros, then
+/// all ones), e.g. `0.0.0.255` is a /24.
+///
+/// # Errors
+/// Returns an error for a non-contiguous wildcard such as `0.255.0.255`.
+pub fn wildcard_to_prefix(wildcard: Ipv4Addr) -> Result<u8> {
+    mask_to_prefix(!wildcard).map_err(|_| eyre!("invalid non-contiguous wildcard {}", wildcard))
+}
+
+/// IPv6 counterpart of `mask_to_prefix`.
+fn v6_mask_to_prefix(mask: Ipv6Addr) -> Result<u8> {
+    let m = u128::from(mask);
+    let carry = (!m).wrapping_add(1);
+    if carry != 0 && !carry.is_power_of_two() {
+        return Err(eyre!("invalid non-contiguous mask {}", mask));
+    }
+    Ok(m.leading_ones() as u8)
+}
+
+/// Apply a separately given prefix length to a bare address, the `--prefix`
+/// counterpart of the netmask form of `parse_network`.
+///
+/// # Errors
+/// Returns an error if the address fails to parse or already carries a
+/// `/prefix`, or if `prefix` is out of range for the address family.
+pub fn parse_with_prefix(address: &str, prefix: u8) -> Result<IpNetwork> {
+    let ip = bare_ip(address, "--prefix")?;
+    check_prefix(u64::from(prefix), ip.is_ipv4())?;
+    IpNetwork::new(ip, prefix).wrap_err("Failed to build network from prefix")
+}
+
+/// Apply a wildcard mask to a bare IPv4 address, as pasted from an ACL line.
+///
+/// # Errors
+/// Returns an error if the address fails to parse, already carries a
+/// `/prefix`, or is IPv6, or if the wildcard is invalid or non-contiguous.
+pub fn parse_with_wildcard(address: &str, wildcard: &str) -> Result<IpNetwork> {
+    let ip = bare_ip(address, "--wildcard")?;
+    let wildcard = wildcard
+        .parse::<Ipv4Addr>()
+        .wrap_err("Invalid wildcard mask")?;
+    if ip.is_ipv6() {
+        return Err(eyre!(
+            "wildcard masks apply to IPv4 addresses only, got '{}'",
+            address
+        ));
+    }
+    IpNetwork::new(ip, wildcard_to_prefix(wildcard)?)
+        .wrap_err("Failed to build network from wildcard")
+}
+
+/// Parse the text after `/` ourselves so typos get a clear message rather
+/// than the library's terse one.
+fn parse_prefix(text: &str, is_v4: bool) -> Result<u8> {
+    let max = if is_v4 { 32 } else { 128 };
+    let family = if is_v4 { "IPv4" } else { "IPv6" };
+    if let Some(digits) = text.strip_prefix('-')
+        && !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(eyre!(
+            "prefix {} is negative; {} prefixes run 0–{}",
+            text,
+            family,
+            max
+        ));
+    }
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(eyre!(
+            "prefix '{}' is not a number; {} prefixes run 0–{}",
+            text,
+            family,
+            max
+        ));
+    }
+    match text.parse::<u64>() {
+        Ok(value) => check_prefix(value, is_v4).map(|()| value as u8),
+        // All digits, so parsing only fails on overflow.
+        Err(_) => Err(eyre!(
+            "prefix {} is out of range for {} (0–{})",
+            text,
+            family,
+            max
+        )),
+    }
+}
+
+/// Reject a prefix longer than the address family allows.
+fn check_prefix(prefix: u64, is_v4: bool) -> Result<()> {
+    let (family, max) = if is_v4 { ("IPv4", 32) } else { ("IPv6", 128) };
+    if prefix > max {
+        return Err(eyre!(
+            "prefix {} is out of range for {} (0–{})",
+            prefix,
+            family,
+            max
+        ));
+    }
+    Ok(())
+}
+
+/// Parse an address that must not carry its own `/prefix` because `flag`
+/// supplies one.
+fn bare_ip(address: &str, flag: &str) -> Result<IpAddr> {
+    if address.contains('/') {
+        return Err(eyre!(
+            "'{}': address already has a prefix; do not combine with {}",
+            address,
+            flag
+        ));
+    }
+    parse_ip(address).wrap_err("Invalid IP address")
+}
+
+/// Compute every displayed field of a network without printing anything.
+pub fn describe(net: &IpNetwork) -> NetworkInfo {
+    let network = net.network();
+    let last = net.broadcast();
+    let total = total_addresses(net);
+    let usable = usable_addresses(net, 2);
+
+    let (broadcast, usable, first_host, last_host) = match (net.is_ipv4(), total) {
+        (_, 1) => (net.is_ipv4().then_some(last), None, None, None),
+        // RFC 3021: both addresses of a /31 point-to-point link are usable.
+        (true, 2) => (Some(last), Some(usable), Some(network), Some(last)),
+        (true, _) => (
+            Some(last),
+            Some(usable),
+            Some(offset(network, 1)),
+            Some(offset(last, -1)),
+        ),
+        (false, _) => (None, Some(usable), Some(network), Some(last)),
+    };
+
+    NetworkInfo {
+        network,
+        broadcast,
+        netmask: net.mask(),
+        prefix: net.prefix(),
+        first_host,
+        last_host,
+        usable,
+        total,
+        network_int: to_bits(network),
+        broadcast_int: broadcast.map(to_bits),
+    }
+}
+
+/// `describe` with `reserved` IPv4 addresses withheld from the usable hosts
+/// instead of the classic two: the first `reserved - 1` and the last, the
+/// layout AWS and Azure use with 5. `/31`, `/32`, and IPv6 networks are
+/// described as usual.
+pub fn describe_reserved(net: &IpNetwork, reserved: u8) -> NetworkInfo {
+    let mut info = describe(net);
+    if !net.is_ipv4() || info.total <= 2 || reserved == 2 {
+        return info;
+    }
+    info.usable = Some(usable_addresses(net, reserved));
+    let reserved = u128::from(reserved);
+    if reserved >= info.total {
+        info.first_host = None;
+        info.last_host = None;
+        return info;
+    }
+    let head = reserved.saturating_sub(1);
+    let tail = reserved.min(1);
+    info.first_host = Some(from_bits(to_bits(info.network) + head, true));
+    info.last_host = Some(from_bits(to_bits(net.broadcast()) - tail, true));
+    info
+}
+
+/// Where an IPv4 prefix falls across the dotted octets: the index (0–3) of
+/// the octet holding the network/host boundary and how many of its bits
+/// belong to the network. A /32 has no host bits and reports `(4, 0)`.
+pub fn octet_boundary(prefix: u8) -> (usize, u8) {
+    (usize::from(prefix / 8), prefix % 8)
+}
+
+/// Cisco-style wildcard (inverse) mask: the bitwise complement of the netmask.
+pub fn wildcard(net: &Ipv4Network) -> Ipv4Addr {
+    !net.mask()
+}
+
+/// Number of `/new_prefix` subnets contained in `net`, saturating at `u128::MAX`.
+///
+/// # Errors
+/// Returns an error unless `new_prefix` is longer than the base prefix and
+/// within the address family's range.
+pub fn subnet_count(net: &IpNetwork, new_prefix: u8) -> Result<u128> {
+    let max = max_prefix(net);
+    if new_prefix <= net.prefix() || new_prefix > max {
+        return Err(eyre!(
+            "new prefix /{} must be longer than /{} and at most /{}",
+            new_prefix,
+            net.prefix(),
+            max
+        ));
+    }
+    Ok(1u128
+        .checked_shl(u32::from(new_prefix - net.prefix()))
+        .unwrap_or(u128::MAX))
+}
+
+/// Divide `net` into every `/new_prefix` subnet it contains, in ascending order.
+///
+/// # Errors
+/// Returns an error if `new_prefix` is not a valid longer prefix (see `subnet_count`).
+pub fn split(net: &IpNetwork, new_prefix: u8) -> Result<Vec<IpNetwork>> {
+    let count = subnet_count(net, new_prefix)?;
+    let is_v4 = net.is_ipv4();
+    let base = to_bits(net.network());
+    let step = 1u128 << (u32::from(max_prefix(net)) - u32::from(new_prefix));
+
+    (0..count)
+        .map(|i| {
+            let addr = from_bits(base + i * step, is_v4);
+            IpNetwork::new(addr, new_prefix).wrap_err("Failed to build subnet")
+        })
+        .collect()
+}
+
+/// Longest valid prefix for the network's address family.
+fn max_prefix(net: &IpNetwork) -> u8 {
+    if net.is_ipv4() { 32 } else { 128 }
+}
+
+/// Widen an address to its integer value.
+fn to_bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(a) => u32::from(a).into(),
+        IpAddr::V6(a) => a.into(),
+    }
+}
+
+/// Rebuild an address of the given family from its integer value.
+fn from_bits(bits: u128, is_v4: bool) -> IpAddr {
+    if is_v4 {
+        IpAddr::V4(Ipv4Addr::from(bits as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(bits))
+    }
+}
+
+/// Total number of addresses in a block: 2^32 for an IPv4 `/0` and 1 for a
+/// `/32` or `/128`, saturating at `u128::MAX` for an IPv6 `/0`. Counts go
+/// through here rather than ad-hoc shifts so no prefix can overflow.
+pub fn total_addresses(net: &IpNetwork) -> u128 {
+    1u128
+        .checked_shl(u32::from(max_prefix(net) - net.prefix()))
+        .unwrap_or(u128::MAX)
+}
+
+/// A count as plain digits, except the saturated `u128::MAX` of an IPv6
+/// `/0`, which no real block has and which is written as the `2^128` it
+/// stands for.
+pub fn count_text(n: u128) -> String {
+    if n == u128::MAX {
+        "2^128".to_string()
+    } else {
+        n.to_string()
+    }
+}
+
+/// Serialize a count as a number, or as `"2^128"` when saturated.
+fn serialize_count<S: Serializer>(n: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    if *n == u128::MAX {
+        serializer.serialize_str(&count_text(*n))
+    } else {
+        serializer.serialize_u128(*n)
+    }
+}
+
+/// `serialize_count` for the optional usable count.
+fn serialize_usable<S: Serializer>(n: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error> {
+    match n {
+        Some(n) => serialize_count(n, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Host addresses left once `reserve` IPv4 addresses are withheld, as
+/// `describe_reserved` counts them: both ends of a `/31` stay usable, a
+/// single-address block has none, and IPv6 reserves nothing.
+pub fn usable_addresses(net: &IpNetwork, reserve: u8) -> u128 {
+    let total = total_addresses(net);
+    match (net.is_ipv4(), total) {
+        (_, 1) => 0,
+        (true, 2) => 2,
+        (true, _) => total.saturating_sub(u128::from(reserve)),
+        (false, _) => total,
+    }
+}
+
+/// Offset an address by a signed delta within its own family.
+fn offset(addr: IpAddr, delta: i8) -> IpAddr {
+    match addr {
+        IpAddr::V4(a) => IpAddr::V4(Ipv4Addr::from(
+            u32::from(a).wrapping_add_signed(delta.into()),
+        )),
+        IpAddr::V6(a) => IpAddr::V6(Ipv6Addr::from(
+            u128::from(a).wrapping_add_signed(delta.into()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(spec: &str) -> IpNetwork {
+        parse_network(spec, None).expect("valid test network")
+    }
+
+    fn ip(addr: &str) -> Option<IpAddr> {
+        Some(addr.parse().expect("valid test address"))
+    }
+
+    #[test]
+    fn describe_whole_v4_space() {
+        let info = describe(&net("0.0.0.0/0"));
+        assert_eq!(info.total, 1 << 32);
+        assert_eq!(info.usable, Some((1 << 32) - 2));
+        assert_eq!(info.first_host, ip("0.0.0.1"));
+        assert_eq!(info.last_host, ip("255.255.255.254"));
+        assert_eq!(info.broadcast, ip("255.255.255.255"));
+    }
+
+    #[test]
+    fn describe_upper_half() {
+        let info = describe(&net("128.0.0.0/1"));
+        assert_eq!(info.total, 1 << 31);
+        assert_eq!(info.usable, Some((1 << 31) - 2));
+        assert_eq!(info.first_host, ip("128.0.0.1"));
+        assert_eq!(info.last_host, ip("255.255.255.254"));
+    }
+
+    #[test]
+    fn describe_single_host() {
+        let info = describe(&net("10.1.2.3/32"));
+        assert_eq!(info.total, 1);
+        assert_eq!(info.usable, None);
+        assert_eq!(info.first_host, None);
+        assert_eq!(info.last_host, None);
+        assert_eq!(info.broadcast, ip("10.1.2.3"));
+    }
+
+    #[test]
+    fn address_counts_at_the_edges() {
+        let cases = [
+            ("0.0.0.0/0", 1 << 32, (1 << 32) - 2),
+            ("10.0.0.0/31", 2, 2),
+            ("10.0.0.0/32", 1, 0),
+            ("2001:db8::/127", 2, 2),
+            ("2001:db8::/128", 1, 0),
+        ];
+        for (spec, total, usable) in cases {
+            assert_eq!(total_addresses(&net(spec)), total, "{}", spec);
+            assert_eq!(usable_addresses(&net(spec), 2), usable, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn usable_addresses_honors_reserve() {
+        assert_eq!(usable_addresses(&net("10.0.0.0/24"), 5), 251);
+        assert_eq!(usable_addresses(&net("10.0.0.0/30"), 5), 0);
+        assert_eq!(usable_addresses(&net("10.0.0.0/31"), 5), 2);
+    }
+
+    #[test]
+    fn whole_v6_space_counts_as_two_to_the_128() {
+        let info = describe(&net("::/0"));
+        assert_eq!(count_text(info.total), "2^128");
+        assert_eq!(count_text(1 << 64), "18446744073709551616");
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["total"], "2^128");
+        assert_eq!(json["usable"], "2^128");
+    }
+
+    #[test]
+    fn point_to_point_31_has_two_hosts() {
+        let info = describe(&net("10.0.0.4/31"));
+        assert_eq!(info.total, 2);
+        assert_eq!(info.usable, Some(2));
+        assert_eq!(info.first_host, ip("10.0.0.4"));
+        assert_eq!(info.last_host, ip("10.0.0.5"));
+        assert_eq!(describe_reserved(&net("10.0.0.4/31"), 5).usable, Some(2));
+    }
+
+    #[test]
+    fn inline_prefix_conflicts_with_a_global_mask() {
+        let err = parse_network("10.0.0.1/24", Some("255.255.255.0")).unwrap_err();
+        assert!(err.to_string().contains("already has a prefix"), "{}", err);
+        assert!(parse_with_prefix("10.0.0.1/24", 16).is_err());
+        assert!(parse_with_wildcard("10.0.0.1/24", "0.0.0.255").is_err());
+        assert_eq!(
+            parse_network("10.0.0.1", Some("255.255.255.0")).unwrap(),
+            net("10.0.0.1/24")
+        );
+    }
+
+    fn expand(args: &[&str]) -> Vec<String> {
+        let raw: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        expand_args(&raw, "192.168.1.1".parse().unwrap()).expect("valid args")
+    }
+
+    #[test]
+    fn comma_list_expands_to_each_network() {
+        assert_eq!(
+            expand(&["10.0.0.0/24, 10.0.1.0/24"]),
+            ["10.0.0.0/24", "10.0.1.0/24"]
+        );
+        assert_eq!(
+            expand(&["10.0.0.0/24 10.0.1.0/24,10.0.2.0/24"]),
+            ["10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24"]
+        );
+    }
+
+    #[test]
+    fn aggregate_composes_with_json() {
+        let halves: Vec<Ipv4Network> = ["10.0.0.0/25", "10.0.0.128/25"]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect();
+        let infos: Vec<NetworkInfo> = aggregate(&halves)
+            .into_iter()
+            .map(|net| describe(&IpNetwork::V4(net)))
+            .collect();
+        let json = serde_json::to_value(&infos).unwrap();
+        assert_eq!(json.as_array().map(Vec::len), Some(1));
+        assert_eq!(json[0]["network"], "10.0.0.0");
+        assert_eq!(json[0]["prefix"], 24);
+        assert_eq!(json[0]["usable"], 254);
+    }
+
+    #[test]
+    fn trailing_comments_become_labels() {
+        let text = "# route dump\n10.0.0.0/24   # prod web\n\n10.0.1.0/24\n10.0.2.0/24 #\n";
+        let lines = read_specs(text.as_bytes()).unwrap();
+        assert_eq!(lines.len(), 3);
+        let mut last_ip = "192.168.1.1".parse().unwrap();
+        let specs = expand_labeled_with(&lines, &mut last_ip).unwrap();
+        assert_eq!(
+            specs,
+            [
+                ("10.0.0.0/24".to_string(), Some("prod web".to_string())),
+                ("10.0.1.0/24".to_string(), None),
+                ("10.0.2.0/24".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn dash_range_needs_address_endpoints() {
+        assert_eq!(
+            dash_range("10.0.0.1-10.0.0.9"),
+            Some((Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 9)))
+        );
+        assert_eq!(
+            dash_range("0x0a000001-0x0a000009"),
+            Some((Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 9)))
+        );
+        assert_eq!(dash_range("10-20"), None);
+        assert_eq!(dash_range("10.0.0.1-20"), None);
+        assert_eq!(dash_range("-10.0.0.1"), None);
+    }
+}
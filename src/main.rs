@@ -1,197 +1,2984 @@
-use clap::Parser;
-use std::env;
-use env_logger;
-use eyre::{Result, WrapErr};
-use ipnetwork::Ipv4Network;
+use cidr::{
+    DEFAULT_NETWORK, Gateway, MaskStyle, NetworkInfo, Relationship, RenderOpts, aggregate,
+    aggregate_lines, anonymize, are_siblings, block_at_prefix, bucket, contains, count_text,
+    describe_reserved, describe_string, diff, eui64, exclude_all, expand_args, expand_args_with,
+    expand_labeled_with, format_count, hex, host_range, host_role, intersect, longest_match,
+    next_network, nth_host, overlapping_pairs, parse_labels, parse_mac, parse_network,
+    parse_with_prefix, parse_with_wildcard, prefix_to_mask, prev_network, ptr_zones,
+    range_to_cidrs, read_specs, relationship, route_prefix, smallest_covering, solicited_node,
+    split, strip_leading_zeros, subnet_count, supernet, total_addresses, usable_addresses,
+    usable_hosts, vlsm, wildcard,
+};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use clap_complete::Shell;
 use colored::*;
+use directories::ProjectDirs;
+use eyre::{Result, WrapErr, eyre};
+use ipnetwork::{IpNetwork, Ipv4Network};
 use log::info;
-use std::net::Ipv4Addr;
-use std::str::FromStr;
-use lazy_static::lazy_static;
-
-lazy_static! {
-    /// Base IP for any leading "/prefix" argument:
-    /// If $DEFAULT_IP is set (and non-empty & valid), we parse just the IP portion
-    /// (dropping any trailing "/24" etc.); otherwise we fall back to "192.168.1.1".
-    static ref DEFAULT_NETWORK: Ipv4Addr = {
-        // grab the raw, or fallback literal
-        let raw = env::var("DEFAULT_NETWORK").unwrap_or_else(|_| "192.168.1.0".into());
-        // drop any "/…" suffix
-        let ip_part = raw.splitn(2, '/').next().unwrap();
-        // parse or warn+fallback
-        ip_part.parse().unwrap_or_else(|_| {
+use notify::{RecursiveMode, Watcher};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Most networks an expanding operation may print without `--force`.
+const OUTPUT_CAP: u128 = 1024;
+
+/// Most levels `--prefix-range` prints without `--force`: a full IPv4 ladder.
+const LADDER_CAP: usize = 33;
+
+/// When to emit ANSI colors.
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ColorMode {
+    /// Color only when stdout is a terminal and $NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+/// Output formats a config file may make the default.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ConfigFormat {
+    Pretty,
+    Json,
+    Yaml,
+    Oneline,
+    Table,
+    Markdown,
+    Csv,
+    Ndjson,
+}
+
+/// Defaults read from `config.toml` in the platform config directory
+/// (`~/.config/cidr/` on Linux) or from $CIDR_CONFIG, e.g.
+///
+/// ```toml
+/// color = "never"
+/// reserve = 5
+/// base = "10.0.0.0"
+/// format = "table"
+/// ```
+///
+/// Anything given on the command line wins.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    color: Option<ColorMode>,
+    reserve: Option<u8>,
+    base: Option<IpAddr>,
+    format: Option<ConfigFormat>,
+}
+
+impl Config {
+    /// Read the config file; a missing file at the default location is no config.
+    fn load() -> Result<Config> {
+        let (path, explicit) = match env::var_os("CIDR_CONFIG") {
+            Some(path) => (PathBuf::from(path), true),
+            None => match ProjectDirs::from("", "", "cidr") {
+                Some(dirs) => (dirs.config_dir().join("config.toml"), false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => {
+                return Ok(Config::default());
+            }
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        toml::from_str(&text).wrap_err_with(|| format!("Invalid config {}", path.display()))
+    }
+
+    /// Fill in whatever the command line left at its default.
+    fn apply(self, cli: &mut Cli, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if let Some(color) = self.color
+            && !given("color")
+            && !cli.no_color
+        {
+            cli.color = color;
+        }
+        if let Some(reserve) = self.reserve
+            && !given("reserve")
+            && cli.cloud.is_none()
+        {
+            cli.reserve = reserve;
+        }
+        // The environment outranks the config file for the base address.
+        let env_base = ["CIDR_DEFAULT_IP", "DEFAULT_NETWORK"]
+            .iter()
+            .any(|var| env::var_os(var).is_some());
+        if cli.base.is_none() && !env_base {
+            cli.base = self.base;
+        }
+        // Clap validated conflicts before the config was read, so a format
+        // the command line rules out (say `--anonymize` and json), or one
+        // that would shadow a pretty-only mode, stays unset.
+        if let Some(format) = self.format
+            && !matches.contains_id("format")
+            && cli.group_by.is_none()
+            && !cli.summary
+            && !format
+                .arg_id()
+                .is_some_and(|id| conflicts_with_given(matches, id))
+        {
+            match format {
+                ConfigFormat::Pretty => {}
+                ConfigFormat::Json => cli.json = true,
+                ConfigFormat::Yaml => cli.yaml = true,
+                ConfigFormat::Oneline => cli.oneline = true,
+                ConfigFormat::Table => cli.table = true,
+                ConfigFormat::Markdown => cli.markdown = true,
+                ConfigFormat::Csv => cli.csv = true,
+                ConfigFormat::Ndjson => cli.ndjson = true,
+            }
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// The flag that selects this format on the command line.
+    fn arg_id(self) -> Option<&'static str> {
+        match self {
+            ConfigFormat::Pretty => None,
+            ConfigFormat::Json => Some("json"),
+            ConfigFormat::Yaml => Some("yaml"),
+            ConfigFormat::Oneline => Some("oneline"),
+            ConfigFormat::Table => Some("table"),
+            ConfigFormat::Markdown => Some("markdown"),
+            ConfigFormat::Csv => Some("csv"),
+            ConfigFormat::Ndjson => Some("ndjson"),
+        }
+    }
+}
+
+/// Whether a flag given on the command line conflicts with the argument `id`,
+/// declared on either side.
+fn conflicts_with_given(matches: &ArgMatches, id: &str) -> bool {
+    let cmd = Cli::command();
+    let Some(target) = cmd.get_arguments().find(|arg| arg.get_id() == id) else {
+        return false;
+    };
+    let against_target = cmd.get_arg_conflicts_with(target);
+    cmd.get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .any(|arg| {
+            against_target.iter().any(|c| c.get_id() == arg.get_id())
+                || cmd
+                    .get_arg_conflicts_with(arg)
+                    .iter()
+                    .any(|c| c.get_id() == id)
+        })
+}
+
+/// A single value `--field` prints per network.
+#[derive(Clone, Copy, ValueEnum)]
+enum Field {
+    Network,
+    Broadcast,
+    Netmask,
+    Wildcard,
+    First,
+    Last,
+    Usable,
+    Total,
+}
+
+/// What `--sort-by` orders networks by; ties fall back to address order.
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    /// Network address, then prefix
+    Addr,
+    /// Prefix length, shortest (largest block) first
+    Prefix,
+    /// Usable host count, fewest first
+    Hosts,
+}
+
+/// Cloud providers whose subnets reserve more than the network and broadcast.
+#[derive(Clone, Copy, ValueEnum)]
+enum Cloud {
+    /// AWS VPC: the first four addresses and the last
+    Aws,
+    /// Azure VNet: the first four addresses and the last
+    Azure,
+}
+
+impl Cloud {
+    /// Addresses the provider withholds from every subnet.
+    fn reserved(self) -> u8 {
+        match self {
+            Cloud::Aws | Cloud::Azure => 5,
+        }
+    }
+}
+
+/// `--gateway` conventions; see `cidr::Gateway`.
+#[derive(Clone, Copy, ValueEnum)]
+enum GatewayChoice {
+    /// The first usable host (`.1` in a /24)
+    First,
+    /// The last usable host (`.254` in a /24)
+    Last,
+}
+
+impl From<GatewayChoice> for Gateway {
+    fn from(choice: GatewayChoice) -> Self {
+        match choice {
+            GatewayChoice::First => Gateway::First,
+            GatewayChoice::Last => Gateway::Last,
+        }
+    }
+}
+
+/// `--mask-style` notations; see `cidr::MaskStyle`.
+#[derive(Clone, Copy, ValueEnum)]
+enum MaskStyleChoice {
+    /// Dotted-decimal (`255.255.255.0`)
+    Dotted,
+    /// Hexadecimal (`0xffffff00`)
+    Hex,
+    /// Prefix length (`/24`)
+    Prefix,
+}
+
+impl From<MaskStyleChoice> for MaskStyle {
+    fn from(choice: MaskStyleChoice) -> Self {
+        match choice {
+            MaskStyleChoice::Dotted => MaskStyle::Dotted,
+            MaskStyleChoice::Hex => MaskStyle::Hex,
+            MaskStyleChoice::Prefix => MaskStyle::Prefix,
+        }
+    }
+}
+
+/// A parsed network and the input text it came from.
+struct Entry {
+    input: String,
+    net: IpNetwork,
+    /// Context printed above the network block, e.g. the hostname it came from
+    label: Option<String>,
+}
+
+/// Wrap networks computed by an operation, using each network as its own input.
+fn derived(nets: impl IntoIterator<Item = IpNetwork>) -> Vec<Entry> {
+    nets.into_iter()
+        .map(|net| Entry {
+            input: net.to_string(),
+            net,
+            label: None,
+        })
+        .collect()
+}
+
+fn networks(entries: &[Entry]) -> Vec<IpNetwork> {
+    entries.iter().map(|e| e.net).collect()
+}
+
+/// Command-line interface
+#[derive(Parser)]
+#[command(name = "cidr", version = env!("GIT_DESCRIBE"), author, about = "Pretty-print CIDR info for one or more addresses")]
+#[command(
+    after_help = "Operations and output formats compose: a transform such as --aggregate, \
+--split, --exclude, --invert, --supernet, --intersect or --next/--prev runs first, and its resulting \
+networks are rendered in the chosen format, e.g. `cidr --aggregate --json 10.0.0.0/25 \
+10.0.0.128/25` prints the merged 10.0.0.0/24 as a JSON array.
+
+Exit status: 0 success, 1 an input failed to parse, 2 the flags were misused \
+(wrong number of networks, IPv6 where IPv4 is required, a prefix or index the \
+network cannot take, ...), 3 a check such as --contains, --overlaps or --min-hosts \
+failed, a --vlsm plan did not fit, or a --geofeed prefix was not a network base.
+
+Defaults for --color, --reserve, --base and the output format (pretty, json, yaml, \
+oneline, table, markdown, csv or ndjson) can be set in ~/.config/cidr/config.toml, or the file \
+named by $CIDR_CONFIG; flags on the command line override them."
+)]
+#[command(group(ArgGroup::new("format").multiple(false)))]
+#[command(group(ArgGroup::new("global_mask").args(["mask", "prefix", "wildcard"]).multiple(false)))]
+struct Cli {
+    /// One or more IPs (with prefix), e.g. 10.10.10.1/16 or 2001:db8::1/64, or
+    /// dashed IPv4 ranges like 10.0.0.5-10.0.0.20;
+    /// `-` (or no addresses with piped input) reads newline-separated specs from stdin
+    #[arg(value_name = "ADDRESS", num_args = 1..)]
+    addresses: Vec<String>,
+
+    /// IP given to a leading "/prefix" input such as `cidr /24`; defaults to
+    /// $CIDR_DEFAULT_IP, then 192.168.1.1
+    #[arg(long = "base", value_name = "IP")]
+    base: Option<IpAddr>,
+
+    /// Optional network mask (e.g. 255.255.248.0, 0xfffff800 or ffff:ffff:ffff:ffff::) for
+    /// addresses without an inline /prefix; those with one keep it, with a warning
+    #[arg(short = 'm', long = "mask", value_name = "MASK")]
+    mask: Option<String>,
+
+    /// Optional prefix length for bare addresses (e.g. 24), instead of /prefix or --mask
+    #[arg(short = 'p', long = "prefix", value_name = "BITS")]
+    prefix: Option<u8>,
+
+    /// Optional Cisco-style wildcard mask for bare IPv4 addresses (e.g. 0.0.0.255 for a /24)
+    #[arg(long = "wildcard", value_name = "WILDCARD")]
+    wildcard: Option<String>,
+
+    /// With --mask, --prefix or --wildcard, drop any inline /prefix from addresses instead of keeping it
+    #[arg(long = "prefer-mask", requires = "global_mask")]
+    prefer_mask: bool,
+
+    /// Emit JSON instead of the colored table (an array for multiple addresses)
+    #[arg(short = 'j', long = "json", group = "format")]
+    json: bool,
+
+    /// Emit one compact JSON object per line, flushed as each is written; piped
+    /// stdin is described line by line as it arrives unless a transform needs
+    /// every input first
+    #[arg(long = "ndjson", group = "format")]
+    ndjson: bool,
+
+    /// Emit YAML instead of the colored table (a sequence for multiple addresses)
+    #[arg(long = "yaml", group = "format")]
+    yaml: bool,
+
+    /// One uncolored line per network, fields in this fixed order:
+    /// `<network>/<prefix> net=<network> bcast=<broadcast> mask=<netmask> hosts=<usable>`
+    /// (`bcast=-` for IPv6)
+    #[arg(short = '1', long = "oneline", group = "format")]
+    oneline: bool,
+
+    /// Print only this value, one per network, with no labels or color (`-` when absent)
+    #[arg(long = "field", value_name = "FIELD", value_enum, group = "format")]
+    field: Option<Field>,
+
+    /// One line per network from a template such as `"{network}/{prefix} {usable}"`;
+    /// tokens: {network} {broadcast} {netmask} {prefix} {first} {last} {usable}
+    /// {total} {hex_network}; `{{` and `}}` print literal braces
+    #[arg(long = "format", value_name = "TEMPLATE", group = "format")]
+    template: Option<String>,
+
+    /// Print shell assignments such as `NAME_NETWORK=10.0.0.0` for `eval`;
+    /// with several networks the names are indexed (`NAME_0_NETWORK`)
+    #[arg(long = "env", value_name = "NAME", group = "format")]
+    env: Option<String>,
+
+    /// Print only the usable host count, one number per network
+    #[arg(short = 'c', long = "count", group = "format")]
+    count: bool,
+
+    /// Print one number: the usable addresses of all networks together, overlaps
+    /// counted once (IPv4)
+    #[arg(long = "count-only", group = "format")]
+    count_only: bool,
+
+    /// Print only the total address count (including network/broadcast), one per network
+    #[arg(short = 't', long = "total", group = "format")]
+    total: bool,
+
+    /// Emit CSV with a header row, one row per network
+    #[arg(long = "csv", group = "format")]
+    csv: bool,
+
+    /// Emit RFC 8805 geofeed rows (prefix,country,region,city,postal) with blank
+    /// geo fields to fill in; inputs that are not a network base fail the check
+    #[arg(long = "geofeed", group = "format")]
+    geofeed: bool,
+
+    /// Annotate each computed value with the arithmetic behind it
+    #[arg(long = "explain", group = "format")]
+    explain: bool,
+
+    /// Aligned table with a header row, one row per network
+    #[arg(long = "table", group = "format")]
+    table: bool,
+
+    /// GitHub-flavored Markdown table, one row per network
+    #[arg(long = "markdown", group = "format")]
+    markdown: bool,
+
+    /// Divide each network into all subnets of this longer prefix
+    #[arg(short = 's', long = "split", value_name = "PREFIX")]
+    split: Option<u8>,
+
+    /// List the --split subnets with a running index, N per group between separators
+    #[arg(
+        long = "group-by",
+        value_name = "N",
+        requires = "split",
+        conflicts_with = "format",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    group_by: Option<u32>,
+
+    /// Count networks by first octet and print a histogram (IPv4)
+    #[arg(long = "by-octet")]
+    by_octet: bool,
+
+    /// Group the input addresses by the /PREFIX block each falls in (IPv4)
+    #[arg(long = "bucket", value_name = "PREFIX", value_parser = clap::value_parser!(u8).range(0..=32))]
+    bucket: Option<u8>,
+
+    /// Print a cheat sheet of each network at every longer prefix: subnet count and size per level
+    #[arg(long = "prefix-range")]
+    prefix_range: bool,
+
+    /// Report how many /PREFIX subnets fit in each network, without listing them
+    #[arg(long = "fit", value_name = "PREFIX")]
+    fit: Option<u8>,
+
+    /// Merge contiguous and overlapping networks into the minimal covering set (IPv4)
+    #[arg(short = 'a', long = "aggregate")]
+    aggregate: bool,
+
+    /// Print the minimal CIDR blocks covering an inclusive IPv4 range
+    #[arg(short = 'r', long = "range", num_args = 2, value_names = ["START", "END"])]
+    range: Option<Vec<Ipv4Addr>>,
+
+    /// Check whether the second network (or host) lies inside the first; exits 3 if not
+    #[arg(long = "contains")]
+    contains: bool,
+
+    /// Print the most specific of the following networks that contains the first
+    /// address, as a route lookup would; exits 3 if none does (IPv4)
+    #[arg(long = "longest-match")]
+    longest_match: bool,
+
+    /// Report every pair of inputs that overlap; exits 3 if any do (IPv4)
+    #[arg(long = "overlaps")]
+    overlaps: bool,
+
+    /// Summarize how exactly two networks relate: equal, nested, siblings, adjacent, or disjoint (IPv4)
+    #[arg(long = "compare")]
+    compare: bool,
+
+    /// Check whether exactly two networks merge into one block; prints it, exits 3 if not (IPv4)
+    #[arg(long = "siblings")]
+    siblings: bool,
+
+    /// Check that every input is exactly its network address (no host bits set); exits 3 if any is not
+    #[arg(long = "assert-network", conflicts_with = "strict")]
+    assert_network: bool,
+
+    /// Check that every network has at least N usable hosts; exits 3 if any falls short
+    #[arg(long = "min-hosts", value_name = "N")]
+    min_hosts: Option<u128>,
+
+    /// Suppress the human-readable verdict of checks; rely on the exit code
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// List the maximal free blocks of PARENT not covered by any --used block (IPv4)
+    #[arg(long = "free", value_name = "PARENT", conflicts_with = "addresses")]
+    free: Option<String>,
+
+    /// Blocks already allocated within the --free parent
+    #[arg(long = "used", value_name = "NETWORK", num_args = 1.., requires = "free")]
+    used: Vec<String>,
+
+    /// Report how much of the first network the remaining ones consume, and the largest free block (IPv4)
+    #[arg(long = "utilization")]
+    utilization: bool,
+
+    /// Allocate subnets sized for these host counts from the network, largest first (IPv4)
+    #[arg(long = "vlsm", value_name = "HOSTS", num_args = 1..)]
+    vlsm: Option<Vec<u32>>,
+
+    /// Replace each network with the next block of the same size (IPv4)
+    #[arg(long = "next", conflicts_with = "prev")]
+    next: bool,
+
+    /// Replace each network with the previous block of the same size (IPv4)
+    #[arg(long = "prev")]
+    prev: bool,
+
+    /// Print the overlap of exactly two networks; exits 3 if they are disjoint (IPv4)
+    #[arg(long = "intersect")]
+    intersect: bool,
+
+    /// Print the parent block of one network, or the smallest block covering several (IPv4)
+    #[arg(long = "supernet")]
+    supernet: bool,
+
+    /// Replace each network with the aligned /PREFIX block containing it (IPv4)
+    #[arg(long = "within", value_name = "PREFIX", value_parser = clap::value_parser!(u8).range(0..=32))]
+    within: Option<u8>,
+
+    /// Remove every following network from the first and print what remains (IPv4)
+    #[arg(short = 'x', long = "exclude")]
+    exclude: bool,
+
+    /// Print the complement of the networks within 0.0.0.0/0, e.g. for
+    /// "deny everything except" ACLs (IPv4)
+    #[arg(long = "invert", conflicts_with = "exclude")]
+    invert: bool,
+
+    /// Print the solicited-node multicast address of each host, one per line (IPv6)
+    #[arg(long = "solicited-node")]
+    solicited_node: bool,
+
+    /// Print the SLAAC address MAC autoconfigures in each /64, from its modified
+    /// EUI-64 interface identifier (IPv6)
+    #[arg(long = "eui64", value_name = "MAC", value_parser = parse_mac)]
+    eui64: Option<[u8; 6]>,
+
+    /// Print every usable host address of each network, one per line (IPv4)
+    #[arg(long = "hosts")]
+    hosts: bool,
+
+    /// Compare two plan files of networks: `-` only in A, `+` only in B, blank in both (IPv4)
+    #[arg(
+        long = "diff",
+        num_args = 2,
+        value_names = ["PLAN_A", "PLAN_B"],
+        conflicts_with = "addresses"
+    )]
+    diff: Option<Vec<PathBuf>>,
+
+    /// Print the netmask, its hex form and wildcard for each prefix length, no address needed
+    #[arg(long = "mask-for", value_name = "PREFIX", num_args = 1.., conflicts_with = "addresses")]
+    mask_for: Option<Vec<u8>>,
+
+    /// Stream IPv4 networks from FILE (`-` for stdin) and print their minimal aggregate, one per line
+    #[arg(long = "merge-file", value_name = "FILE", conflicts_with = "addresses")]
+    merge_file: Option<PathBuf>,
+
+    /// Render the networks listed in FILE, re-rendering on a cleared screen
+    /// whenever it changes
+    #[arg(long = "watch", value_name = "FILE", conflicts_with = "addresses")]
+    watch: Option<PathBuf>,
+
+    /// Read `ip route` output from stdin and print the minimal set of blocks covering
+    /// its destinations; default and IPv6 routes are skipped
+    #[arg(long = "summarize-routes", conflicts_with = "addresses")]
+    summarize_routes: bool,
+
+    /// Print a shell completion script for SHELL and exit
+    #[arg(long = "completions", value_name = "SHELL", hide = true)]
+    completions: Option<Shell>,
+
+    /// Show each input's trailing `# comment` above its network
+    #[arg(long = "comments")]
+    comments: bool,
+
+    /// Resolve hostname inputs (e.g. example.com/24) to their A records, one network per record
+    #[arg(long = "resolve")]
+    resolve: bool,
+
+    /// Prompt for CIDRs one line at a time, printing each, until EOF or `quit`;
+    /// `/prefix` reuses the last IP across prompts
+    #[arg(short = 'i', long = "interactive", conflicts_with = "addresses")]
+    interactive: bool,
+
+    /// Print whether each address is its block's network, broadcast, first or
+    /// last host, or a middle host (IPv4)
+    #[arg(long = "role")]
+    role: bool,
+
+    /// Print the INDEXth usable host of each network, counting from 1; negative
+    /// indices count back from the last host (-1) (IPv4)
+    #[arg(long = "pick", value_name = "INDEX", allow_negative_numbers = true)]
+    pick: Option<i64>,
+
+    /// Print N distinct random usable hosts drawn uniformly from each network (IPv4)
+    #[arg(long = "random", value_name = "N")]
+    random: Option<usize>,
+
+    /// Print N distinct random /PREFIX subnets drawn uniformly from each network (IPv4)
+    #[arg(long = "random-subnet", num_args = 2, value_names = ["PREFIX", "N"])]
+    random_subnet: Option<Vec<usize>>,
+
+    /// Seed for --random and --random-subnet, for reproducible samples
+    #[arg(long = "seed", value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// Print the in-addr.arpa zones delegating each network (IPv4)
+    #[arg(long = "ptr")]
+    ptr: bool,
+
+    /// When to color output
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        value_enum,
+        default_value = "auto"
+    )]
+    color: ColorMode,
+
+    /// Abbreviate address counts with metric suffixes (16.7M) instead of thousands separators
+    #[arg(long = "si")]
+    si: bool,
+
+    /// Disable colored output (same as --color never)
+    #[arg(long = "no-color", conflicts_with = "color")]
+    no_color: bool,
+
+    /// Also show the address and mask in binary, marking the prefix boundary with `|`
+    #[arg(short = 'b', long = "binary")]
+    binary: bool,
+
+    /// Also show the legacy classful (A-E) interpretation of each address and the
+    /// subnet bits borrowed from its default mask
+    #[arg(long = "class")]
+    class: bool,
+
+    /// Replace host octets with `x` (10.0.x.x/16) so output can be shared without
+    /// revealing the addresses; drops the hex, integer and binary views
+    #[arg(
+        long = "anonymize",
+        conflicts_with_all = [
+            "json", "ndjson", "yaml", "csv", "geofeed", "field", "template", "env", "explain",
+            "hosts", "pick", "random"
+        ]
+    )]
+    anonymize: bool,
+
+    /// Parse inputs, and render the pretty, --json, --yaml and --oneline output,
+    /// on N threads (rayon) for very large lists, keeping input order. Other
+    /// formats and the transforms stay sequential, as does everything when omitted
+    #[arg(
+        long = "jobs",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    jobs: Option<u32>,
+
+    /// Read zero-padded octets such as `010.000.000.001` as decimal instead of rejecting them
+    #[arg(long = "lenient")]
+    lenient: bool,
+
+    /// Error when an address has host bits set instead of silently normalizing it
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Warn when a block does not start on its prefix boundary and suggest the aligned network
+    #[arg(long = "align")]
+    align: bool,
+
+    /// TOML file of `name = "cidr"` pairs; each network gets a "Label:" row naming
+    /// the most specific block containing it (IPv4)
+    #[arg(long = "labels", value_name = "FILE")]
+    labels: Option<PathBuf>,
+
+    /// Label the first or last usable host as the gateway (IPv4)
+    #[arg(long = "gateway", value_name = "WHICH", value_enum)]
+    gateway: Option<GatewayChoice>,
+
+    /// Write the Netmask row as dotted-decimal, hex or /prefix to match the
+    /// target platform's config syntax
+    #[arg(
+        long = "mask-style",
+        value_name = "STYLE",
+        value_enum,
+        default_value = "dotted"
+    )]
+    mask_style: MaskStyleChoice,
+
+    /// Show the address as typed in a "Host:" row next to the computed network
+    #[arg(long = "host")]
+    host: bool,
+
+    /// IPv4 addresses to withhold from the usable hosts: the first N-1 and the last
+    #[arg(long = "reserve", value_name = "N", default_value_t = 2)]
+    reserve: u8,
+
+    /// Reserve addresses the way this cloud provider does (5 for AWS and Azure)
+    #[arg(
+        long = "cloud",
+        value_name = "PROVIDER",
+        value_enum,
+        conflicts_with = "reserve"
+    )]
+    cloud: Option<Cloud>,
+
+    /// Also show the IPv4-mapped IPv6 address and 6to4 prefix of each address
+    #[arg(long = "v6map")]
+    v6map: bool,
+
+    /// Also show the network and broadcast addresses as unsigned integers
+    #[arg(long = "int")]
+    int: bool,
+
+    /// Also break the address into octets, marking which bits are network and host
+    #[arg(long = "octets")]
+    octets: bool,
+
+    /// Drop repeated networks (same network address and prefix), keeping the first seen
+    #[arg(long = "dedup")]
+    dedup: bool,
+
+    /// Print networks in ascending numeric order (network address, then prefix)
+    #[arg(long = "sort", conflicts_with = "sort_desc")]
+    sort: bool,
+
+    /// Print networks in descending numeric order
+    #[arg(long = "sort-desc")]
+    sort_desc: bool,
+
+    /// Sort by this key instead of address (implies --sort; combine with --sort-desc to reverse)
+    #[arg(long = "sort-by", value_name = "KEY", value_enum)]
+    sort_by: Option<SortKey>,
+
+    /// Finish with a footer totalling the networks, their de-duplicated usable
+    /// addresses, and the overall span (IPv4)
+    #[arg(long = "summary")]
+    summary: bool,
+
+    /// Report unparsable inputs on stderr and continue; exits 1 if any failed
+    #[arg(short = 'k', long = "keep-going")]
+    keep_going: bool,
+
+    /// Allow operations to print more than 1024 networks
+    #[arg(long = "force")]
+    force: bool,
+}
+
+/// Process exit status. Scripts branch on these, so the values are fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitCode {
+    Success = 0,
+    /// An input address, mask, range or file line did not parse.
+    Parse = 1,
+    /// The flags were combined or fed the wrong inputs.
+    Usage = 2,
+    /// A check mode such as `--contains` or `--min-hosts` reached a negative verdict.
+    CheckFailed = 3,
+}
+
+impl ExitCode {
+    fn exit(self) -> ! {
+        process::exit(self as i32)
+    }
+
+    /// Exit with a check mode's verdict.
+    fn verdict(ok: bool) -> ! {
+        if ok {
+            ExitCode::Success
+        } else {
+            ExitCode::CheckFailed
+        }
+        .exit()
+    }
+}
+
+/// An error in how the tool was invoked rather than in an input;
+/// `main` maps it to `ExitCode::Usage`.
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// A negative verdict reported as an error, e.g. a VLSM plan that does not
+/// fit; `main` maps it to `ExitCode::CheckFailed`.
+#[derive(Debug)]
+struct CheckError(String);
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// Like `eyre!`, but the error exits with `ExitCode::Usage`.
+macro_rules! usage {
+    ($($arg:tt)*) => {
+        eyre::Report::new(UsageError(format!($($arg)*)))
+    };
+}
+
+/// Re-raise a library error caused by a flag's value (a `--split` prefix
+/// shorter than the network, say) as a usage error, keeping its message.
+fn as_usage(err: eyre::Report) -> eyre::Report {
+    usage!("{:#}", err)
+}
+
+/// Re-raise a library error that is really a negative verdict (the VLSM
+/// requests do not fit) as a check failure, keeping its message.
+fn as_check(err: eyre::Report) -> eyre::Report {
+    eyre::Report::new(CheckError(format!("{:#}", err)))
+}
+
+fn main() {
+    let code = match try_main() {
+        Ok(()) => ExitCode::Success,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            exit_code(&err)
+        }
+    };
+    code.exit()
+}
+
+/// The exit status an error reports: usage and check errors anywhere in the
+/// chain win, and everything else is an input that failed to parse.
+fn exit_code(err: &eyre::Report) -> ExitCode {
+    if err.chain().any(|cause| cause.is::<UsageError>()) {
+        ExitCode::Usage
+    } else if err.chain().any(|cause| cause.is::<CheckError>()) {
+        ExitCode::CheckFailed
+    } else {
+        ExitCode::Parse
+    }
+}
+
+fn try_main() -> Result<()> {
+    env_logger::init();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    Config::load()?.apply(&mut cli, &matches);
+    if let Some(shell) = cli.completions {
+        clap_complete::generate(shell, &mut Cli::command(), "cidr", &mut io::stdout());
+        return Ok(());
+    }
+    apply_color(cli.color, cli.no_color);
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs as usize)
+            .build_global()
+            .wrap_err("Failed to start the --jobs thread pool")?;
+    }
+    if cli.interactive {
+        return repl(&cli);
+    }
+    if let Some(plans) = &cli.diff {
+        return diff_plans(&cli, &plans[0], &plans[1]);
+    }
+    if let Some(path) = &cli.merge_file {
+        return merge_file(path);
+    }
+    if let Some(prefixes) = &cli.mask_for {
+        for &prefix in prefixes {
+            let mask = prefix_to_mask(prefix)?;
+            println!(
+                "{}  {}  {:<15}  {} {}",
+                format!("{:>3}", format!("/{}", prefix)).bold().magenta(),
+                hex(IpAddr::V4(mask)).bright_black(),
+                mask.to_string().cyan(),
+                "wildcard".yellow(),
+                (!mask).to_string().cyan()
+            );
+        }
+        return Ok(());
+    }
+    if let Some(path) = &cli.watch {
+        return watch(&cli, path);
+    }
+    if cli.summarize_routes {
+        let mut prefixes = Vec::new();
+        for line in io::stdin().lock().lines() {
+            let line = line.wrap_err("Failed to read routes from stdin")?;
+            prefixes.extend(route_prefix(&line));
+        }
+        return run(
+            &cli,
+            derived(aggregate(&prefixes).into_iter().map(IpNetwork::V4)),
+        );
+    }
+    if cli.ndjson && cli.addresses.is_empty() && streams(&matches) && !io::stdin().is_terminal() {
+        return stream_ndjson(&cli);
+    }
+    let mut last_ip = base_ip(&cli);
+    let specs = expand_labeled_with(
+        &raw_inputs(
+            &cli,
+            gather_args(&cli.addresses, cli.range.is_none() && cli.free.is_none())?,
+        ),
+        &mut last_ip,
+    )?;
+
+    let (mut entries, failed) = parse_specs(&cli, &specs)?;
+    if let Some(range) = &cli.range {
+        let blocks = range_to_cidrs(range[0], range[1])?;
+        entries.extend(derived(blocks.into_iter().map(IpNetwork::V4)));
+    }
+
+    run(&cli, entries)?;
+    if failed > 0 {
+        ExitCode::Parse.exit();
+    }
+    Ok(())
+}
+
+/// Parse expanded specs into entries, attaching `--comments` labels. Under
+/// `--keep-going` failures are reported and counted instead of returned.
+fn parse_specs(cli: &Cli, specs: &[(String, Option<String>)]) -> Result<(Vec<Entry>, usize)> {
+    let mut entries = Vec::with_capacity(specs.len());
+    let mut failed = 0;
+    // Sequential parsing stops at the first error; under --jobs every spec
+    // is parsed up front and the first failure in input order is reported.
+    let parse = |(spec, _): &(String, Option<String>)| parse_spec(cli, spec);
+    let parsed: Box<dyn Iterator<Item = Result<Vec<Entry>>>> = match cli.jobs {
+        Some(_) => Box::new(ordered_map(cli, specs, parse).into_iter()),
+        None => Box::new(specs.iter().map(parse)),
+    };
+    for ((_, comment), result) in specs.iter().zip(parsed) {
+        match result {
+            Ok(mut parsed) => {
+                if let (true, Some(comment)) = (cli.comments, comment) {
+                    for entry in &mut parsed {
+                        entry.label = Some(match &entry.label {
+                            Some(label) => format!("{}  # {}", label, comment),
+                            None => format!("# {}", comment),
+                        });
+                    }
+                }
+                entries.extend(parsed);
+            }
+            Err(err) if cli.keep_going => {
+                eprintln!("error: {:#}", err);
+                failed += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((entries, failed))
+}
+
+/// `items.map(f)` in input order, spread over the `--jobs` pool when one was
+/// requested.
+fn ordered_map<T: Sync, R: Send>(
+    cli: &Cli,
+    items: &[T],
+    f: impl Fn(&T) -> R + Sync + Send,
+) -> Vec<R> {
+    match cli.jobs {
+        Some(_) => items.par_iter().map(f).collect(),
+        None => items.iter().map(f).collect(),
+    }
+}
+
+/// Input text as typed, or with zero-padded octets read as decimal under `--lenient`.
+fn raw_inputs(cli: &Cli, args: Vec<String>) -> Vec<String> {
+    if cli.lenient {
+        args.iter().map(|arg| strip_leading_zeros(arg)).collect()
+    } else {
+        args
+    }
+}
+
+/// The IP a leading "/prefix" input expands against.
+fn base_ip(cli: &Cli) -> IpAddr {
+    cli.base.unwrap_or(*DEFAULT_NETWORK)
+}
+
+/// The optional `print_network` rows selected on the command line.
+fn render_opts(cli: &Cli) -> Result<RenderOpts> {
+    let labels = match &cli.labels {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            parse_labels(&text).wrap_err_with(|| format!("In {}", path.display()))?
+        }
+        None => Vec::new(),
+    };
+    Ok(RenderOpts {
+        binary: cli.binary,
+        class: cli.class,
+        host: cli.host,
+        v6map: cli.v6map,
+        int: cli.int,
+        octets: cli.octets,
+        reserve: cli.cloud.map_or(cli.reserve, Cloud::reserved),
+        gateway: cli.gateway.map(Gateway::from),
+        si: cli.si,
+        labels,
+        anonymize: cli.anonymize,
+        mask_style: cli.mask_style.into(),
+    })
+}
+
+/// Print the network-level difference between two plan files.
+fn diff_plans(cli: &Cli, a: &Path, b: &Path) -> Result<()> {
+    let load = |path: &Path| -> Result<Vec<Ipv4Network>> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+        let specs = expand_args(
+            &raw_inputs(cli, read_specs(BufReader::new(file))?),
+            base_ip(cli),
+        )?;
+        let mut nets = Vec::with_capacity(specs.len());
+        for spec in &specs {
+            let entry =
+                parse_entry(cli, spec).wrap_err_with(|| format!("In {}", path.display()))?;
+            nets.push(entry.net);
+        }
+        v4_only(&nets, "--diff")
+    };
+    let plan = diff(&load(a)?, &load(b)?);
+
+    let mut lines: Vec<(Ipv4Network, char)> = plan
+        .only_a
+        .iter()
+        .map(|net| (*net, '-'))
+        .chain(plan.only_b.iter().map(|net| (*net, '+')))
+        .chain(plan.both.iter().map(|net| (*net, ' ')))
+        .collect();
+    lines.sort_by_key(|(net, _)| (net.network(), net.prefix()));
+    for (net, mark) in lines {
+        let line = format!("{} {}", mark, net);
+        match mark {
+            '-' => println!("{}", line.red()),
+            '+' => println!("{}", line.green()),
+            _ => println!("{}", line),
+        }
+    }
+    Ok(())
+}
+
+/// Aggregate a large network list straight from the file to stdout, with no
+/// per-network rendering.
+fn merge_file(path: &Path) -> Result<()> {
+    let merged = if path == Path::new("-") {
+        aggregate_lines(io::stdin().lock())?
+    } else {
+        let file =
+            File::open(path).wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+        aggregate_lines(BufReader::new(file)).wrap_err_with(|| format!("In {}", path.display()))?
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    for net in merged {
+        writeln!(out, "{}", net)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `--watch`: render `path`, then again after every change to it. The parent
+/// directory is watched so editors that save by renaming a new file into
+/// place are still seen; bursts of events collapse into one render.
+fn watch(cli: &Cli, path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).wrap_err("Failed to start file watcher")?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .wrap_err_with(|| format!("Failed to watch {}", dir.display()))?;
+    let name = path.file_name();
+    loop {
+        print!("\x1b[2J\x1b[H");
+        println!("{}", format!("watching {}", path.display()).bright_black());
+        if let Err(err) = render_file(cli, path) {
+            eprintln!("error: {:#}", err);
+        }
+        io::stdout().flush()?;
+        // Block until an event touches the file, then let the burst settle.
+        loop {
+            let event = rx.recv().wrap_err("File watcher stopped")?;
+            // Our own reads raise access events; only changes count.
+            if let Ok(event) = event
+                && !event.kind.is_access()
+                && event.paths.iter().any(|p| p.file_name() == name)
+            {
+                break;
+            }
+        }
+        while rx.recv_timeout(Duration::from_millis(100)).is_ok() {}
+    }
+}
+
+/// One `--watch` render. A save can leave the file briefly missing or
+/// empty, so reads are retried for a moment before giving up.
+fn render_file(cli: &Cli, path: &Path) -> Result<()> {
+    let mut attempts = 0;
+    let text = loop {
+        attempts += 1;
+        match fs::read_to_string(path) {
+            Ok(text) if !text.trim().is_empty() || attempts == 5 => break text,
+            Err(err) if attempts == 5 => {
+                return Err(err).wrap_err_with(|| format!("Failed to read {}", path.display()));
+            }
+            _ => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+    let mut last_ip = base_ip(cli);
+    let specs = expand_labeled_with(&raw_inputs(cli, read_specs(text.as_bytes())?), &mut last_ip)?;
+    let (entries, _) = parse_specs(cli, &specs)?;
+    run(cli, entries)
+}
+
+/// Flags that only shape how each input is read or counted, never looking at
+/// the other inputs, so `--ndjson` can stream stdin under them. Anything else
+/// on the command line may transform the whole list and disables streaming.
+const STREAMABLE: [&str; 19] = [
+    // Argument groups, including the one clap derives for the whole struct.
+    "Cli",
+    "format",
+    "global_mask",
+    "ndjson",
+    "mask",
+    "prefix",
+    "wildcard",
+    "prefer_mask",
+    "strict",
+    "align",
+    "lenient",
+    "base",
+    "reserve",
+    "cloud",
+    "keep_going",
+    "resolve",
+    "color",
+    "no_color",
+    "jobs",
+];
+
+/// Whether every flag given on the command line is in `STREAMABLE`.
+fn streams(matches: &ArgMatches) -> bool {
+    matches.ids().all(|id| {
+        STREAMABLE.contains(&id.as_str())
+            || matches.value_source(id.as_str()) != Some(ValueSource::CommandLine)
+    })
+}
+
+/// `--ndjson` over piped stdin: describe each line as it is read so
+/// downstream tools see results before the input ends.
+fn stream_ndjson(cli: &Cli) -> Result<()> {
+    colored::control::set_override(false);
+    let reserve = render_opts(cli)?.reserve;
+    let mut last_ip = base_ip(cli);
+    let mut failed = 0;
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.wrap_err("Failed to read input")?;
+        let raw = raw_inputs(cli, read_specs(line.as_bytes())?);
+        let (entries, bad) = parse_specs(cli, &expand_labeled_with(&raw, &mut last_ip)?)?;
+        failed += bad;
+        for entry in &entries {
+            write_ndjson(&mut out, &describe_reserved(&entry.net, reserve))?;
+        }
+    }
+    if failed > 0 {
+        ExitCode::Parse.exit();
+    }
+    Ok(())
+}
+
+/// Read specs from stdin line by line and print each network as it arrives.
+/// Errors are reported and the prompt continues.
+fn repl(cli: &Cli) -> Result<()> {
+    let opts = render_opts(cli)?;
+    let mut last_ip = base_ip(cli);
+    let mut line = String::new();
+    loop {
+        print!("cidr> ");
+        io::stdout().flush()?;
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let input = line.trim();
+        match input {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            _ => {}
+        }
+        let specs = match expand_args_with(&raw_inputs(cli, vec![input.to_string()]), &mut last_ip)
+        {
+            Ok(specs) => specs,
+            Err(err) => {
+                eprintln!("error: {:#}", err);
+                continue;
+            }
+        };
+        for spec in &specs {
+            match parse_entry(cli, spec) {
+                Ok(entry) => print_network(&entry.net, &opts),
+                Err(err) => eprintln!("error: {:#}", err),
+            }
+        }
+    }
+}
+
+/// Parse a spec into its entries: one normally, or one per A record when
+/// `--resolve` is set and the address part is a hostname.
+fn parse_spec(cli: &Cli, spec: &str) -> Result<Vec<Entry>> {
+    match parse_entry(cli, spec) {
+        Ok(entry) => Ok(vec![entry]),
+        Err(_) if cli.resolve && !spec.contains(':') => resolve_spec(cli, spec),
+        Err(err) => Err(err),
+    }
+}
+
+/// Look up the IPv4 addresses of a `host[/prefix]` spec and parse each with
+/// the spec's prefix, labelling the entries with the hostname.
+fn resolve_spec(cli: &Cli, spec: &str) -> Result<Vec<Entry>> {
+    let (host, prefix) = match spec.split_once('/') {
+        Some((host, prefix)) => (host, Some(prefix)),
+        None => (spec, None),
+    };
+    let mut addrs: Vec<Ipv4Addr> = Vec::new();
+    let resolved = (host, 0)
+        .to_socket_addrs()
+        .wrap_err_with(|| format!("Invalid input '{}': could not resolve '{}'", spec, host))?;
+    for addr in resolved {
+        if let IpAddr::V4(v4) = addr.ip()
+            && !addrs.contains(&v4)
+        {
+            addrs.push(v4);
+        }
+    }
+    if addrs.is_empty() {
+        return Err(eyre!(
+            "Invalid input '{}': '{}' has no IPv4 (A) records",
+            spec,
+            host
+        ));
+    }
+
+    let mut entries = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        let resolved_spec = match prefix {
+            Some(prefix) => format!("{}/{}", addr, prefix),
+            None => addr.to_string(),
+        };
+        let mut entry = parse_entry(cli, &resolved_spec)?;
+        info!("Resolved {} to {}", host, addr);
+        entry.input = spec.to_string();
+        let shown_addr = shown(IpAddr::V4(addr), entry.net.prefix(), cli.anonymize);
+        entry.label = Some(format!("{} ({})", host, shown_addr));
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Parse networks given as a flag's values, with the same expansion and
+/// validation as positional addresses.
+fn flag_networks(cli: &Cli, specs: &[String], flag: &str) -> Result<Vec<Ipv4Network>> {
+    let mut nets = Vec::with_capacity(specs.len());
+    for spec in expand_args(&raw_inputs(cli, specs.to_vec()), base_ip(cli))? {
+        nets.push(parse_entry(cli, &spec)?.net);
+    }
+    v4_only(&nets, flag)
+}
+
+/// Parse one expanded spec under the global mask and validation flags.
+///
+/// A global mask applies only to specs without an inline `/prefix`; a spec
+/// with one keeps it (with a warning) unless `--prefer-mask` drops it.
+fn parse_entry(cli: &Cli, spec: &str) -> Result<Entry> {
+    let addr = match spec.split_once('/') {
+        Some((addr, _)) if cli.prefer_mask => addr,
+        _ => spec,
+    };
+    let parsed = match (overridden_mask(cli, spec), cli.prefix, &cli.wildcard) {
+        (Some(flag), _, _) => {
             eprintln!(
-                "warning: env $DEFAULT_NETWORK ('{}') invalid; using 192.168.1.0",
-                raw
+                "warning: {} keeps its inline prefix; {} applies only to addresses without one",
+                spec, flag
             );
-            "192.168.1.1".parse().unwrap()
-        })
+            parse_network(spec, None)
+        }
+        (None, Some(prefix), _) => parse_with_prefix(addr, prefix),
+        (None, None, Some(wildcard)) => parse_with_wildcard(addr, wildcard),
+        (None, None, None) => parse_network(addr, cli.mask.as_deref()),
+    };
+    let net = parsed.wrap_err_with(|| format!("Invalid input '{}'", spec))?;
+    info!("Parsed network: {}", net);
+    if cli.strict && net.ip() != net.network() {
+        return Err(eyre!(
+            "{} has host bits set; the network is {}/{}",
+            spec,
+            net.network(),
+            net.prefix()
+        ));
+    }
+    if cli.align && net.ip() != net.network() {
+        warn_misaligned(&net, cli.si);
+    }
+    Ok(Entry {
+        input: spec.to_string(),
+        net,
+        label: None,
+    })
+}
+
+/// The global mask flag that `spec`'s own inline prefix overrides, if any;
+/// `--prefer-mask` lets the flag win instead.
+fn overridden_mask(cli: &Cli, spec: &str) -> Option<&'static str> {
+    if !spec.contains('/') || cli.prefer_mask {
+        return None;
+    }
+    match (cli.prefix, &cli.wildcard, &cli.mask) {
+        (Some(_), _, _) => Some("--prefix"),
+        (None, Some(_), _) => Some("--wildcard"),
+        (None, None, Some(_)) => Some("--mask"),
+        (None, None, None) => None,
+    }
+}
+
+/// Explain on stderr how far a block's address sits past its prefix boundary.
+fn warn_misaligned(net: &IpNetwork, si: bool) {
+    let offset = match (net.ip(), net.network()) {
+        (IpAddr::V4(ip), IpAddr::V4(base)) => u128::from(ip.to_bits() - base.to_bits()),
+        (IpAddr::V6(ip), IpAddr::V6(base)) => ip.to_bits() - base.to_bits(),
+        _ => unreachable!("network and address share a family"),
+    };
+    let step = match total_addresses(net) {
+        u128::MAX => count_text(u128::MAX),
+        n => format_count(n, si),
     };
+    eprintln!(
+        "warning: {}/{} is misaligned: a /{} starts every {} addresses and {} is {} past \
+         the boundary; use {}/{}",
+        net.ip(),
+        net.prefix(),
+        net.prefix(),
+        step,
+        net.ip(),
+        format_count(offset, si),
+        net.network(),
+        net.prefix()
+    );
 }
 
-/// Command-line interface
-#[derive(Parser)]
-#[command(name = "cidr", version = env!("GIT_DESCRIBE"), author, about = "Pretty-print CIDR info for one or more addresses")]
-struct Cli {
-    /// One or more IPs (with prefix), e.g. 10.10.10.1/16
-    #[arg(value_name = "ADDRESS", num_args = 1..)]
-    addresses: Vec<String>,
+/// Apply the selected operation to the parsed entries and render the result.
+///
+/// Check modes exit with their verdict. Otherwise a transform (at most one)
+/// replaces the entries with its result, `--dedup` and `--sort` reorder them,
+/// and whichever output format was chosen renders what is left, so every
+/// transform works with every format.
+fn run(cli: &Cli, mut entries: Vec<Entry>) -> Result<()> {
+    let mut nets = networks(&entries);
+
+    let opts = render_opts(cli)?;
+
+    if cli.contains {
+        let (outer, inner) = v4_pair(&nets, "--contains")?;
+        let inside = contains(&outer, &inner);
+        if !cli.quiet {
+            let verdict = if inside { "yes:" } else { "no:" };
+            println!("{} {} in {}", verdict, inner, outer);
+        }
+        ExitCode::verdict(inside);
+    }
+
+    if cli.longest_match {
+        let v4 = v4_only(&nets, "--longest-match")?;
+        let (target, candidates) = match &v4[..] {
+            [target, candidates @ ..] if !candidates.is_empty() => (target.ip(), candidates),
+            _ => {
+                return Err(usage!(
+                    "--longest-match takes a target address and at least one candidate network"
+                ));
+            }
+        };
+        let best = longest_match(target, candidates);
+        match best {
+            Some(best) => println!("{}", best),
+            None if cli.quiet => {}
+            None => println!("no match: no candidate contains {}", target),
+        }
+        ExitCode::verdict(best.is_some());
+    }
+
+    if cli.overlaps {
+        let v4 = v4_only(&nets, "--overlaps")?;
+        let pairs = overlapping_pairs(&v4);
+        if !cli.quiet {
+            for &(i, j) in &pairs {
+                let inner = if v4[i].prefix() >= v4[j].prefix() {
+                    v4[i]
+                } else {
+                    v4[j]
+                };
+                println!(
+                    "{} overlaps {}: {} - {}",
+                    v4[i],
+                    v4[j],
+                    inner.network(),
+                    inner.broadcast()
+                );
+            }
+        }
+        ExitCode::verdict(pairs.is_empty());
+    }
+
+    if cli.compare {
+        let (a, b) = v4_pair(&nets, "--compare")?;
+        let summary = match relationship(&a, &b) {
+            Relationship::Equal => format!("equal: {} and {} are the same network", a, b),
+            Relationship::Contains => format!("contains: {} contains {}", a, b),
+            Relationship::ContainedBy => format!("contained: {} lies inside {}", a, b),
+            Relationship::Siblings(parent) => {
+                format!("siblings: {} + {} = {}", a, b, parent)
+            }
+            Relationship::Adjacent => format!(
+                "adjacent: {} and {} touch but do not merge into one block",
+                a, b
+            ),
+            Relationship::Disjoint { gap } => format!(
+                "disjoint: {} and {} are {} addresses apart",
+                a,
+                b,
+                format_count(gap.into(), opts.si)
+            ),
+        };
+        println!("{}", summary);
+        return Ok(());
+    }
+
+    if cli.siblings {
+        let (a, b) = v4_pair(&nets, "--siblings")?;
+        let merged = are_siblings(&a, &b).then(|| supernet(&a)).flatten();
+        if !cli.quiet {
+            match merged {
+                Some(parent) => println!("yes: {} + {} = {}", a, b, parent),
+                None => println!("no: {} and {} are not mergeable", a, b),
+            }
+        }
+        ExitCode::verdict(merged.is_some());
+    }
+
+    if cli.assert_network {
+        let mut all_ok = true;
+        for entry in &entries {
+            let net = entry.net;
+            let ok = net.ip() == net.network();
+            all_ok &= ok;
+            if cli.quiet {
+                continue;
+            }
+            if ok {
+                println!("ok: {}", entry.input);
+            } else {
+                println!(
+                    "fail: {} has host bits set; the network is {}/{}",
+                    entry.input,
+                    net.network(),
+                    net.prefix()
+                );
+            }
+        }
+        ExitCode::verdict(all_ok);
+    }
+
+    if let Some(min) = cli.min_hosts {
+        let mut all_fit = true;
+        for net in &nets {
+            let usable = usable_addresses(net, opts.reserve);
+            let fits = usable >= min;
+            all_fit &= fits;
+            if !cli.quiet {
+                let verdict = if fits { "pass:" } else { "fail:" };
+                println!(
+                    "{} {} has {} usable hosts (need {})",
+                    verdict,
+                    net,
+                    format_count(usable, opts.si),
+                    min
+                );
+            }
+        }
+        ExitCode::verdict(all_fit);
+    }
+
+    if cli.utilization {
+        let v4 = v4_only(&nets, "--utilization")?;
+        let (base, allocs) = v4
+            .split_first()
+            .ok_or_else(|| usage!("--utilization needs a base network"))?;
+        return print_utilization(base, allocs, opts.si);
+    }
+
+    if let Some(hosts) = &cli.vlsm {
+        let base = match v4_only(&nets, "--vlsm")?[..] {
+            [base] => base,
+            _ => return Err(usage!("--vlsm takes exactly one base network")),
+        };
+        return print_vlsm(&base, hosts, &opts);
+    }
+
+    if cli.intersect {
+        let (a, b) = v4_pair(&nets, "--intersect")?;
+        match intersect(&a, &b) {
+            Some(common) => nets = vec![IpNetwork::V4(common)],
+            None => {
+                if !cli.quiet {
+                    println!("no overlap: {} and {} are disjoint", a, b);
+                }
+                ExitCode::CheckFailed.exit();
+            }
+        }
+    }
+
+    if cli.supernet {
+        let v4 = v4_only(&nets, "--supernet")?;
+        let parent = match &v4[..] {
+            [] => return Err(usage!("--supernet needs at least one network")),
+            [one] => supernet(one).ok_or_else(|| usage!("{} has no supernet", one))?,
+            [first, rest @ ..] => rest
+                .iter()
+                .fold(*first, |acc, net| smallest_covering(&acc, net)),
+        };
+        nets = vec![IpNetwork::V4(parent)];
+    }
+
+    let transformed = cli.supernet
+        || cli.free.is_some()
+        || cli.within.is_some()
+        || cli.intersect
+        || cli.exclude
+        || cli.invert
+        || cli.next
+        || cli.prev
+        || cli.aggregate
+        || cli.split.is_some();
+    if let Some(parent) = &cli.free {
+        let parent = match flag_networks(cli, std::slice::from_ref(parent), "--free")?[..] {
+            [parent] => parent,
+            _ => return Err(usage!("--free takes exactly one parent network")),
+        };
+        let used = flag_networks(cli, &cli.used, "--used")?;
+        nets = aggregate(&exclude_all(&parent, &used))
+            .into_iter()
+            .map(IpNetwork::V4)
+            .collect();
+    }
+    if let Some(prefix) = cli.within {
+        nets = v4_only(&nets, "--within")?
+            .iter()
+            .map(|net| {
+                if prefix > net.prefix() {
+                    return Err(usage!(
+                        "--within /{} is longer than {}; pick a prefix of /{} or shorter",
+                        prefix,
+                        net,
+                        net.prefix()
+                    ));
+                }
+                Ok(IpNetwork::V4(block_at_prefix(net.ip(), prefix)))
+            })
+            .collect::<Result<_>>()?;
+    }
+    if cli.exclude {
+        let v4 = v4_only(&nets, "--exclude")?;
+        let (base, holes) = v4
+            .split_first()
+            .ok_or_else(|| usage!("--exclude needs a base network"))?;
+        nets = exclude_all(base, holes)
+            .into_iter()
+            .map(IpNetwork::V4)
+            .collect();
+    }
+    if cli.invert {
+        let holes = v4_only(&nets, "--invert")?;
+        let everything = Ipv4Network::new(Ipv4Addr::UNSPECIFIED, 0).expect("/0 is valid");
+        nets = exclude_all(&everything, &holes)
+            .into_iter()
+            .map(IpNetwork::V4)
+            .collect();
+    }
+    if cli.next || cli.prev {
+        type Step = fn(&Ipv4Network) -> Option<Ipv4Network>;
+        let (flag, dir, step): (&str, &str, Step) = if cli.next {
+            ("--next", "next", next_network)
+        } else {
+            ("--prev", "previous", prev_network)
+        };
+        nets = v4_only(&nets, flag)?
+            .iter()
+            .map(|net| {
+                step(net)
+                    .map(IpNetwork::V4)
+                    .ok_or_else(|| usage!("{} has no {} block in the address space", net, dir))
+            })
+            .collect::<Result<_>>()?;
+    }
+    if cli.aggregate {
+        let merged = aggregate(&v4_only(&nets, "--aggregate")?);
+        nets = merged.into_iter().map(IpNetwork::V4).collect();
+    }
+    if let Some(new_prefix) = cli.split {
+        nets = split_all(&nets, new_prefix, cli.force)?;
+    }
+    if transformed {
+        entries = derived(nets.iter().copied());
+    }
+    if cli.dedup {
+        let mut seen = HashSet::new();
+        entries.retain(|e| seen.insert((e.net.network(), e.net.prefix())));
+        nets = networks(&entries);
+    }
+    if cli.sort || cli.sort_desc || cli.sort_by.is_some() {
+        // Address order matches `cidr::sort_networks`, extended to mixed IPv4/IPv6 input.
+        let addr = |e: &Entry| (e.net.network(), e.net.prefix());
+        match cli.sort_by.unwrap_or(SortKey::Addr) {
+            SortKey::Addr => entries.sort_by_key(addr),
+            SortKey::Prefix => entries.sort_by_key(|e| (e.net.prefix(), addr(e))),
+            SortKey::Hosts => entries.sort_by_key(|e| {
+                let usable = describe_reserved(&e.net, opts.reserve).usable;
+                (usable.unwrap_or(0), addr(e))
+            }),
+        }
+        if cli.sort_desc {
+            entries.reverse();
+        }
+        nets = networks(&entries);
+    }
+
+    if cli.by_octet {
+        print_octet_histogram(&v4_only(&nets, "--by-octet")?);
+        return Ok(());
+    }
+
+    if let Some(prefix) = cli.bucket {
+        let addrs: Vec<Ipv4Addr> = v4_only(&nets, "--bucket")?
+            .iter()
+            .map(|net| net.ip())
+            .collect();
+        for (i, (block, members)) in bucket(&addrs, prefix).iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let noun = if members.len() == 1 { "host" } else { "hosts" };
+            println!(
+                "{} {}",
+                format!("{}:", block).bold().magenta(),
+                format!("({} {})", members.len(), noun).bright_black()
+            );
+            for host in members {
+                println!("  {}", host.to_string().cyan());
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(prefix) = cli.fit {
+        for net in &nets {
+            let count = subnet_count(net, prefix).map_err(as_usage)?;
+            let subnet = IpNetwork::new(net.network(), prefix)?;
+            let usable = usable_addresses(&subnet, opts.reserve);
+            println!(
+                "{} holds {} /{} subnets of {} usable hosts each",
+                net,
+                format_count(count, opts.si),
+                prefix,
+                format_count(usable, opts.si)
+            );
+        }
+        return Ok(());
+    }
+
+    if cli.hosts {
+        let v4 = v4_only(&nets, "--hosts")?;
+        let count: u128 = v4
+            .iter()
+            .filter_map(|net| host_range(net, opts.reserve))
+            .map(|(first, last)| u128::from(u32::from(last) - u32::from(first)) + 1)
+            .sum();
+        if count > OUTPUT_CAP && !cli.force {
+            return Err(usage!(
+                "--hosts would print {} addresses (limit {}); pass --force to print them all",
+                count,
+                OUTPUT_CAP
+            ));
+        }
+        for net in &v4 {
+            for host in usable_hosts(net, opts.reserve) {
+                println!("{}", host);
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.role {
+        for net in v4_only(&nets, "--role")? {
+            println!("{}", host_role(net.ip(), &net));
+        }
+        return Ok(());
+    }
+
+    if let Some(i) = cli.pick {
+        for net in v4_only(&nets, "--pick")? {
+            let host = nth_host(&net, i, opts.reserve).ok_or_else(|| {
+                let count = host_range(&net, opts.reserve).map_or(0, |(first, last)| {
+                    u128::from(u32::from(last) - u32::from(first)) + 1
+                });
+                usage!(
+                    "{} has no usable host {}; it has {}",
+                    net,
+                    i,
+                    format_count(count, opts.si)
+                )
+            })?;
+            println!("{}", host);
+        }
+        return Ok(());
+    }
+
+    if let Some(n) = cli.random {
+        let mut rng = seeded_rng(cli.seed);
+        for net in v4_only(&nets, "--random")? {
+            // A reservation that swallows the block leaves nothing to draw.
+            let (first, len) = match host_range(&net, opts.reserve) {
+                Some((first, last)) => (
+                    u32::from(first),
+                    (u32::from(last) - u32::from(first)) as usize + 1,
+                ),
+                None => (0, 0),
+            };
+            for i in sample_indices(&mut rng, len, n, "--random", "usable hosts", &net)? {
+                println!("{}", Ipv4Addr::from(first + i as u32));
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(args) = &cli.random_subnet {
+        let prefix = u8::try_from(args[0])
+            .map_err(|_| usage!("Invalid --random-subnet prefix {}", args[0]))?;
+        let mut rng = seeded_rng(cli.seed);
+        for net in v4_only(&nets, "--random-subnet")? {
+            let len = subnet_count(&IpNetwork::V4(net), prefix).map_err(as_usage)? as usize;
+            let base = u64::from(u32::from(net.network()));
+            let step = 1u64 << (32 - prefix);
+            let what = format!("/{} subnets", prefix);
+            for i in sample_indices(&mut rng, len, args[1], "--random-subnet", &what, &net)? {
+                let addr = Ipv4Addr::from((base + i as u64 * step) as u32);
+                println!("{}", Ipv4Network::new(addr, prefix)?);
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.prefix_range {
+        for (i, net) in nets.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print_ladder(net, opts.reserve, opts.si, cli.force)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(mac) = cli.eui64 {
+        for net in &nets {
+            match net {
+                IpNetwork::V6(v6) if v6.prefix() <= 64 => println!("{}", eui64(*v6, mac)),
+                _ => {
+                    return Err(usage!(
+                        "--eui64 needs IPv6 prefixes of /64 or shorter, got {}",
+                        net
+                    ));
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.solicited_node {
+        for net in &nets {
+            match net {
+                IpNetwork::V6(v6) => println!("{}", solicited_node(v6.ip())),
+                IpNetwork::V4(_) => {
+                    return Err(usage!(
+                        "--solicited-node supports IPv6 addresses only, got {}",
+                        net
+                    ));
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.ptr {
+        for net in v4_only(&nets, "--ptr")? {
+            print_ptr(&net, cli.force);
+        }
+        return Ok(());
+    }
+
+    if cli.json {
+        colored::control::set_override(false);
+        let infos = ordered_map(cli, &nets, |net| describe_reserved(net, opts.reserve));
+        return print_json(&infos);
+    }
+
+    if cli.ndjson {
+        colored::control::set_override(false);
+        let mut out = io::stdout().lock();
+        for net in &nets {
+            write_ndjson(&mut out, &describe_reserved(net, opts.reserve))?;
+        }
+        return Ok(());
+    }
+
+    if cli.yaml {
+        colored::control::set_override(false);
+        let infos = ordered_map(cli, &nets, |net| describe_reserved(net, opts.reserve));
+        print_yaml(&infos);
+        return Ok(());
+    }
+
+    if cli.csv {
+        colored::control::set_override(false);
+        print_csv(&entries, opts.reserve);
+        return Ok(());
+    }
+
+    if cli.geofeed {
+        colored::control::set_override(false);
+        for net in &nets {
+            // --align has already warned while parsing.
+            if !cli.align && net.ip() != net.network() {
+                warn_misaligned(net, opts.si);
+            }
+        }
+        for row in geofeed_rows(&nets)? {
+            println!("{}", row);
+        }
+        return Ok(());
+    }
+
+    if cli.markdown {
+        colored::control::set_override(false);
+        print_markdown(&nets, opts.reserve, opts.anonymize, opts.si);
+        return Ok(());
+    }
+
+    if cli.table {
+        print_table(&nets, opts.reserve, opts.anonymize, opts.si);
+        return Ok(());
+    }
+
+    if let Some(field) = cli.field {
+        colored::control::set_override(false);
+        for net in &nets {
+            println!("{}", field_value(net, field, opts.reserve));
+        }
+        return Ok(());
+    }
+
+    if let Some(name) = &cli.env {
+        colored::control::set_override(false);
+        return print_env(name, &nets, opts.reserve);
+    }
 
-    /// Optional network mask (e.g. 255.255.248.0)
-    #[arg(short = 'm', long = "mask", value_name = "MASK")]
-    mask: Option<String>,
-}
+    if let Some(template) = &cli.template {
+        let pieces = parse_template(template)?;
+        colored::control::set_override(false);
+        for net in &nets {
+            println!("{}", render_template(&pieces, net, opts.reserve));
+        }
+        return Ok(());
+    }
 
-fn main() -> Result<()> {
-    env_logger::init();
-    let cli = Cli::parse();
-    let specs = expand_args(&cli.addresses)?;
+    if cli.count_only {
+        let merged = aggregate(&v4_only(&nets, "--count-only")?);
+        println!("{}", usable_total(&merged, opts.reserve));
+        return Ok(());
+    }
 
-    for (i, spec) in specs.iter().enumerate() {
-        let net = parse_network(spec, cli.mask.as_deref())?;
-        info!("Parsed network: {}", net);
-        print_network(&net);
+    if cli.count || cli.total {
+        for net in &nets {
+            let info = describe_reserved(net, opts.reserve);
+            let n = if cli.count {
+                info.usable.unwrap_or(0)
+            } else {
+                info.total
+            };
+            println!("{}", count_text(n));
+        }
+        return Ok(());
+    }
+
+    if cli.oneline {
+        let lines = ordered_map(cli, &nets, |net| {
+            oneline(
+                &describe_reserved(net, opts.reserve),
+                opts.anonymize,
+                opts.si,
+            )
+        });
+        for line in lines {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    if let Some(size) = cli.group_by {
+        print!("{}", grouped(&nets, size as usize, &opts));
+        return Ok(());
+    }
 
-        if i + 1 < specs.len() {
+    let blocks = ordered_map(cli, &entries, |entry| {
+        (!cli.explain).then(|| describe_string(&entry.net, &opts))
+    });
+    for (i, (entry, block)) in entries.iter().zip(blocks).enumerate() {
+        if let Some(label) = &entry.label {
+            println!("{}", label.bright_black());
+        }
+        match block {
+            Some(block) => print!("{}", block),
+            None => print_explain(&entry.net, opts.reserve, opts.si),
+        }
+
+        if i + 1 < entries.len() {
             println!();
         }
     }
 
+    if cli.summary {
+        println!();
+        print!("{}", summary(&nets, &opts));
+    }
+
     Ok(())
 }
 
-/// Expand a mix of full “IP/prefix” and “/prefix” args into all full specs,
-/// defaulting the first-ever prefix-only to 192.168.1.1.
-///
-/// # Errors
-/// Returns an error if any provided IP or prefix fails to parse.
-fn expand_args(raw_args: &[String]) -> Result<Vec<String>> {
-    let mut last_ip: Option<Ipv4Addr> = Some(*DEFAULT_NETWORK);
-    let mut out = Vec::with_capacity(raw_args.len());
+/// Globally enable or disable `colored` output so every styled string obeys
+/// the chosen mode.
+fn apply_color(mode: ColorMode, no_color: bool) {
+    let enabled = match (mode, no_color) {
+        (_, true) | (ColorMode::Never, _) => false,
+        (ColorMode::Always, _) => true,
+        (ColorMode::Auto, _) => {
+            env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+        }
+    };
+    colored::control::set_override(enabled);
+}
 
-    for raw in raw_args {
-        let spec = if raw.starts_with('/') {
-            let tok = &raw[1..];
-            if tok.contains('/') {
-                let mut parts = tok.splitn(2, '/');
-                let ip_str = parts.next().unwrap();
-                last_ip = Some(Ipv4Addr::from_str(ip_str)?);
-                tok.to_string()
-            } else {
-                let ip = last_ip.unwrap();
-                format!("{}/{}", ip, tok)
-            }
-        } else {
-            let mut parts = raw.splitn(2, '/');
-            let ip_str = parts.next().unwrap();
-            last_ip = Some(Ipv4Addr::from_str(ip_str)?);
-            raw.clone()
-        };
+/// Narrow the inputs to IPv4 for operations that only support that family.
+fn v4_only(nets: &[IpNetwork], flag: &str) -> Result<Vec<Ipv4Network>> {
+    nets.iter()
+        .map(|net| match net {
+            IpNetwork::V4(v4) => Ok(*v4),
+            IpNetwork::V6(_) => Err(usage!("{} supports IPv4 networks only, got {}", flag, net)),
+        })
+        .collect()
+}
 
-        out.push(spec);
+/// Require exactly two IPv4 inputs for pairwise checks.
+fn v4_pair(nets: &[IpNetwork], flag: &str) -> Result<(Ipv4Network, Ipv4Network)> {
+    match v4_only(nets, flag)?[..] {
+        [a, b] => Ok((a, b)),
+        _ => Err(usage!(
+            "{} takes exactly two networks, got {}",
+            flag,
+            nets.len()
+        )),
     }
+}
 
-    Ok(out)
+/// The sampling RNG: deterministic under `--seed`, OS-seeded otherwise.
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => rand::make_rng(),
+    }
 }
 
-/// Parse either “addr/prefix” or “addr” + separate netmask into an `Ipv4Network`
-fn parse_network(address: &str, mask: Option<&str>) -> Result<Ipv4Network> {
-    if let Some(mask_str) = mask {
-        let ip = address
-            .parse::<Ipv4Addr>()
-            .wrap_err("Invalid IP address")?;
-        let mask_ip = mask_str
-            .parse::<Ipv4Addr>()
-            .wrap_err("Invalid network mask")?;
-        let mask_u32 = u32::from(mask_ip);
-        let prefix = (32 - mask_u32.trailing_zeros()) as u8;
-        Ipv4Network::new(ip, prefix).wrap_err("Failed to build network from mask")
-    } else {
-        Ipv4Network::from_str(address).wrap_err("Invalid address/prefix format")
+/// `n` distinct indices drawn uniformly from `0..len`, refusing to draw more
+/// than `net` has.
+fn sample_indices(
+    rng: &mut StdRng,
+    len: usize,
+    n: usize,
+    flag: &str,
+    what: &str,
+    net: &Ipv4Network,
+) -> Result<Vec<usize>> {
+    if n > len {
+        return Err(usage!(
+            "{} {} exceeds the {} {} in {}",
+            flag,
+            n,
+            len,
+            what,
+            net
+        ));
+    }
+    Ok(index::sample(rng, len, n).into_vec())
+}
+
+/// Replace every network with its `/new_prefix` subnets, refusing to flood
+/// the terminal past `OUTPUT_CAP` unless forced.
+fn split_all(nets: &[IpNetwork], new_prefix: u8, force: bool) -> Result<Vec<IpNetwork>> {
+    let mut total: u128 = 0;
+    for net in nets {
+        total = total.saturating_add(subnet_count(net, new_prefix).map_err(as_usage)?);
+    }
+    if total > OUTPUT_CAP && !force {
+        return Err(usage!(
+            "--split /{} would print {} subnets (limit {}); pass --force to print them all",
+            new_prefix,
+            total,
+            OUTPUT_CAP
+        ));
+    }
+
+    let mut out = Vec::new();
+    for net in nets {
+        out.extend(split(net, new_prefix)?);
     }
+    Ok(out)
 }
 
-/// Pretty-print network info with right-justified, colored labels
-/// (computed width), and “Total Addresses:” at the bottom.
-fn print_network(net: &Ipv4Network) {
-    let prefix = net.prefix();
-    let netaddr = net.network();
-    let bcast   = net.broadcast();
-    let mask    = net.mask();
-    let count: u64 = 1 << (32 - prefix);
+/// Splice stdin into the positional list wherever `-` appears, or read it
+/// wholesale when no addresses were given, stdin is piped, and no other
+/// option supplies the input.
+fn gather_args(raw_args: &[String], allow_piped: bool) -> Result<Vec<String>> {
+    if raw_args.is_empty() && allow_piped && !io::stdin().is_terminal() {
+        return read_specs(io::stdin().lock());
+    }
 
-    let mut labels = vec![
-        "Network:",
-        "Broadcast:",
-        "Netmask:",
-        "First Host:",
-        "Last Host:",
-        "Addresses:",
-    ];
-    if count == 1 {
-        labels = vec!["1 Address Total:"];
+    let mut out = Vec::with_capacity(raw_args.len());
+    for raw in raw_args {
+        if raw == "-" {
+            out.extend(read_specs(io::stdin().lock())?);
+        } else {
+            out.push(raw.clone());
+        }
     }
+    Ok(out)
+}
 
-    let label_width = labels
+/// Compact indexed listing, `size` networks per group under a separator
+/// naming the index range, so long `--split` results stay navigable.
+fn grouped(nets: &[IpNetwork], size: usize, opts: &RenderOpts) -> String {
+    let mut lines = Vec::new();
+    let rows: Vec<(String, String, u128)> = nets
         .iter()
-        .map(|s| s.len())
-        .max()
-        .expect("at least one label");
+        .map(|net| {
+            let info = describe_reserved(net, opts.reserve);
+            let addr = |a: IpAddr| shown(a, info.prefix, opts.anonymize);
+            let range = match (info.first_host, info.last_host) {
+                (Some(first), Some(last)) => format!("{} - {}", addr(first), addr(last)),
+                _ => addr(info.network),
+            };
+            let net = format!("{}/{}", addr(net.ip()), info.prefix);
+            (net, range, info.usable.unwrap_or(0))
+        })
+        .collect();
+    let index_width = rows.len().to_string().len();
+    let net_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let range_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+    for (g, group) in rows.chunks(size).enumerate() {
+        if g > 0 {
+            lines.push(String::new());
+        }
+        let start = g * size + 1;
+        lines.push(
+            format!(
+                "── {}-{} of {} ──",
+                start,
+                start + group.len() - 1,
+                rows.len()
+            )
+            .bright_black()
+            .to_string(),
+        );
+        for (i, (net, range, usable)) in group.iter().enumerate() {
+            lines.push(format!(
+                "  {}  {}  {}  {}",
+                format!("{:>index_width$}", start + i).yellow(),
+                format!("{:<net_width$}", net).magenta(),
+                format!("{:<range_width$}", range).cyan(),
+                format!("({} usable)", format_count(*usable, opts.si)).bright_red()
+            ));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Histogram of networks per first octet, bars scaled to the busiest octet.
+fn print_octet_histogram(nets: &[Ipv4Network]) {
+    const BAR_WIDTH: usize = 40;
+    let mut counts: BTreeMap<u8, usize> = BTreeMap::new();
+    for net in nets {
+        *counts.entry(net.network().octets()[0]).or_default() += 1;
+    }
+    let busiest = counts.values().copied().max().unwrap_or(0);
+    let count_width = busiest.to_string().len();
+    for (octet, count) in &counts {
+        let bar = (count * BAR_WIDTH).div_ceil(busiest);
+        println!(
+            "  {}  {:>count_width$}  {}",
+            format!("{:>3}", octet).yellow(),
+            count,
+            "█".repeat(bar).cyan()
+        );
+    }
+}
+
+/// Subnet cheat sheet: one row per prefix from the network's own down to a
+/// single address, truncated past `LADDER_CAP` levels unless forced.
+fn print_ladder(net: &IpNetwork, reserve: u8, si: bool, force: bool) -> Result<()> {
+    let max = if net.is_ipv4() { 32 } else { 128 };
+    let levels = usize::from(max - net.prefix()) + 1;
+    let shown = if force {
+        levels
+    } else {
+        levels.min(LADDER_CAP)
+    };
 
-    let pad_label = |s: &str| format!("{:>width$}", s, width = label_width).yellow();
+    let header = ["Prefix", "Netmask", "Subnets", "Addresses", "Usable"];
+    let mut rows = Vec::with_capacity(shown);
+    for prefix in (net.prefix()..=max).take(shown) {
+        let subnets = if prefix == net.prefix() {
+            1
+        } else {
+            subnet_count(net, prefix)?
+        };
+        let info = describe_reserved(&IpNetwork::new(net.network(), prefix)?, reserve);
+        rows.push([
+            format!("/{}", prefix),
+            info.netmask.to_string(),
+            format_count(subnets, si),
+            format_count(info.total, si),
+            format_count(info.usable.unwrap_or(0), si),
+        ]);
+    }
 
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
     println!(
         "{}",
-        format!("{}/{}:", netaddr, prefix)
+        format!("{}/{}:", net.network(), net.prefix())
             .bold()
             .magenta()
     );
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, w))| match i {
+                0 | 1 => format!("{:<w$}", cell),
+                _ => format!("{:>w$}", cell),
+            })
+            .collect();
+        padded.join("  ")
+    };
+    println!("  {}", line(&header.map(String::from)).bold());
+    for row in &rows {
+        println!("  {}", line(row));
+    }
+    if shown < levels {
+        eprintln!(
+            "warning: {} has {} prefix levels; showing the first {} (pass --force for all)",
+            net, levels, shown
+        );
+    }
+    Ok(())
+}
+
+/// List a network's reverse zones, truncating past `OUTPUT_CAP` unless forced.
+fn print_ptr(net: &Ipv4Network, force: bool) {
+    let zones = ptr_zones(net);
+    let total = zones.len();
+    let shown = if force {
+        total
+    } else {
+        total.min(OUTPUT_CAP as usize)
+    };
+    for zone in zones.take(shown) {
+        println!("{}", zone);
+    }
+    if shown < total {
+        eprintln!(
+            "warning: {} spans {} zones; showing the first {} (pass --force for all)",
+            net, total, shown
+        );
+    }
+}
+
+/// Print each VLSM allocation followed by the free space left in the base.
+fn print_vlsm(base: &Ipv4Network, hosts: &[u32], opts: &RenderOpts) -> Result<()> {
+    let allocated = vlsm(base, hosts, opts.reserve).map_err(as_check)?;
+    for (want, net) in &allocated {
+        println!("{}", format!("# {} hosts", want).bright_black());
+        print_network(&IpNetwork::V4(*net), opts);
+        println!();
+    }
+
+    let used: Vec<Ipv4Network> = allocated.iter().map(|&(_, net)| net).collect();
+    let free = exclude_all(base, &used);
+    let free_total: u128 = free
+        .iter()
+        .map(|net| total_addresses(&IpNetwork::V4(*net)))
+        .sum();
+    let blocks: Vec<String> = free.iter().map(ToString::to_string).collect();
+    println!(
+        "{} {} addresses{}",
+        "Free:".yellow(),
+        format_count(free_total, opts.si),
+        if blocks.is_empty() {
+            String::new()
+        } else {
+            format!(" in {}", blocks.join(", "))
+        }
+    );
+    Ok(())
+}
+
+/// Share of `base` covered by `allocs` (overlaps counted once, anything
+/// outside `base` ignored), what remains, and the largest free block.
+fn print_utilization(base: &Ipv4Network, allocs: &[Ipv4Network], si: bool) -> Result<()> {
+    let size = |net: &Ipv4Network| total_addresses(&IpNetwork::V4(*net));
+    let mut inside = Vec::with_capacity(allocs.len());
+    for alloc in allocs {
+        match intersect(base, alloc) {
+            Some(common) => inside.push(common),
+            None => eprintln!("warning: {} lies outside {}; ignoring it", alloc, base),
+        }
+    }
+    let used = aggregate(&inside);
+    let total = size(base);
+    let used_total: u128 = used.iter().map(size).sum();
+    let free = exclude_all(base, &used);
+    let largest = free.iter().min_by_key(|net| (net.prefix(), net.network()));
+    let pct = |n: u128| 100.0 * n as f64 / total as f64;
 
+    let label = |s: &str| format!("{:>13}", s).yellow();
+    println!("{}", format!("{}:", base).bold().magenta());
     println!(
-        "  {}  {}  {:<16}",
-        pad_label("Network:"),
-        format!("0x{:08x}", u32::from(netaddr)).bright_black(),
-        netaddr.to_string().cyan()
+        "  {}  {} of {} addresses ({:.1}%)",
+        label("Allocated:"),
+        format_count(used_total, si),
+        format_count(total, si),
+        pct(used_total)
     );
     println!(
-        "  {}  {}  {:<16}",
-        pad_label("Broadcast:"),
-        format!("0x{:08x}", u32::from(bcast)).bright_black(),
-        bcast.to_string().cyan()
+        "  {}  {} addresses ({:.1}%)",
+        label("Free:"),
+        format_count(total - used_total, si),
+        pct(total - used_total)
     );
     println!(
-        "  {}  {}  {:<16}",
-        pad_label("Netmask:"),
-        format!("0x{:08x}", u32::from(mask)).bright_black(),
-        mask.to_string().cyan()
+        "  {}  {}",
+        label("Largest Free:"),
+        largest
+            .map_or("none".to_string(), |net| net.to_string())
+            .cyan()
     );
+    Ok(())
+}
+
+/// Print a single object for one network, or an array for several.
+fn print_json(infos: &[NetworkInfo]) -> Result<()> {
+    let out = match infos {
+        [one] => serde_json::to_string_pretty(one)?,
+        many => serde_json::to_string_pretty(many)?,
+    };
+    println!("{}", out);
+    Ok(())
+}
+
+/// One compact JSON object and a newline, flushed so a consumer reading the
+/// pipe sees it immediately.
+fn write_ndjson(out: &mut impl Write, info: &NetworkInfo) -> Result<()> {
+    serde_json::to_writer(&mut *out, info)?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Print a single mapping for one network, or a sequence for several.
+fn print_yaml(infos: &[NetworkInfo]) {
+    if infos.is_empty() {
+        println!("[]");
+    }
+    for info in infos {
+        for (i, (key, value)) in yaml_fields(info).into_iter().enumerate() {
+            let lead = match (infos.len(), i) {
+                (1, _) => "",
+                (_, 0) => "- ",
+                _ => "  ",
+            };
+            println!("{}{}: {}", lead, key, value);
+        }
+    }
+}
+
+/// `NetworkInfo` as YAML scalars, in its serialized field order. serde_yaml
+/// cannot be asked for a quoting style and leaves addresses plain, so the
+/// mapping is written here: addresses as single-quoted strings, counts as
+/// bare numbers (the saturated `2^128` quoted) and absent fields as `null`.
+fn yaml_fields(info: &NetworkInfo) -> [(&'static str, String); 10] {
+    let addr = |a: Option<IpAddr>| a.map_or("null".to_string(), |a| format!("'{}'", a));
+    let count = |n: Option<u128>| match n {
+        Some(u128::MAX) => format!("'{}'", count_text(u128::MAX)),
+        Some(n) => n.to_string(),
+        None => "null".to_string(),
+    };
+    [
+        ("network", addr(Some(info.network))),
+        ("broadcast", addr(info.broadcast)),
+        ("netmask", addr(Some(info.netmask))),
+        ("prefix", info.prefix.to_string()),
+        ("first_host", addr(info.first_host)),
+        ("last_host", addr(info.last_host)),
+        ("usable", count(info.usable)),
+        ("total", count(Some(info.total))),
+        ("network_int", info.network_int.to_string()),
+        (
+            "broadcast_int",
+            info.broadcast_int
+                .map_or("null".to_string(), |n| n.to_string()),
+        ),
+    ]
+}
+
+/// One RFC 8805 geofeed row per network, geo fields left blank.
+///
+/// # Errors
+/// A check failure naming every network given by an address other than its
+/// base, because a geofeed prefix must be the network itself.
+fn geofeed_rows(nets: &[IpNetwork]) -> Result<Vec<String>> {
+    let misaligned: Vec<String> = nets
+        .iter()
+        .filter(|net| net.ip() != net.network())
+        .map(|net| format!("{}/{}", net.ip(), net.prefix()))
+        .collect();
+    if !misaligned.is_empty() {
+        return Err(as_check(eyre!(
+            "geofeed prefixes must be network bases: {}",
+            misaligned.join(", ")
+        )));
+    }
+    Ok(nets
+        .iter()
+        .map(|net| format!("{}/{},,,,", net.network(), net.prefix()))
+        .collect())
+}
+
+/// Header and one row per entry; host fields are blank when the block has none.
+fn print_csv(entries: &[Entry], reserve: u8) {
+    println!("input,network,prefix,broadcast,netmask,first_host,last_host,usable,total");
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    for entry in entries {
+        let info = describe_reserved(&entry.net, reserve);
+        let fields = [
+            entry.input.clone(),
+            info.network.to_string(),
+            info.prefix.to_string(),
+            opt(info.broadcast.map(|a| a.to_string())),
+            info.netmask.to_string(),
+            opt(info.first_host.map(|a| a.to_string())),
+            opt(info.last_host.map(|a| a.to_string())),
+            opt(info.usable.map(count_text)),
+            count_text(info.total),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        println!("{}", row.join(","));
+    }
+}
 
-    if count == 1 {
-        println!("  {}", pad_label("1 Address Total:"));
-        return;
+/// One row per network under a bold header, column widths sized to the data.
+fn print_table(nets: &[IpNetwork], reserve: u8, anon: bool, si: bool) {
+    let header = ["Network", "Prefix", "Broadcast", "Netmask", "Usable"];
+    let rows: Vec<[String; 5]> = nets
+        .iter()
+        .map(|net| {
+            let info = describe_reserved(net, reserve);
+            [
+                shown(info.network, info.prefix, anon),
+                format!("/{}", info.prefix),
+                info.broadcast
+                    .map_or("-".to_string(), |b| shown(b, info.prefix, anon)),
+                info.netmask.to_string(),
+                format_count(info.usable.unwrap_or(0), si),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
     }
 
-    let usable = if count > 2 { count - 2 } else { 0 };
-    if usable > 0 {
-        let first = Ipv4Addr::from(u32::from(netaddr) + 1);
-        let last  = Ipv4Addr::from(u32::from(bcast) - 1);
+    // Usable is right-aligned; pad before styling so escape codes don't
+    // count toward the width.
+    let line = |cells: Vec<&str>| -> Vec<String> {
+        cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, w))| {
+                if i == widths.len() - 1 {
+                    format!("{:>w$}", cell)
+                } else {
+                    format!("{:<w$}", cell)
+                }
+            })
+            .collect()
+    };
+    let styled: Vec<String> = line(header.to_vec())
+        .iter()
+        .map(|h| h.bold().to_string())
+        .collect();
+    println!("{}", styled.join("  "));
+    for row in &rows {
         println!(
-            "  {}  {}  {:<16}",
-            pad_label("First Host:"),
-            format!("0x{:08x}", u32::from(first)).bright_black(),
-            first.to_string().cyan()
+            "{}",
+            line(row.iter().map(String::as_str).collect()).join("  ")
         );
+    }
+}
+
+/// Markdown table with a separator row; Usable is right-aligned.
+fn print_markdown(nets: &[IpNetwork], reserve: u8, anon: bool, si: bool) {
+    println!("| Network | Broadcast | Netmask | Usable |");
+    println!("| --- | --- | --- | ---: |");
+    for net in nets {
+        let info = describe_reserved(net, reserve);
         println!(
-            "  {}  {}  {:<16}",
-            pad_label("Last Host:"),
-            format!("0x{:08x}", u32::from(last)).bright_black(),
-            last.to_string().cyan()
+            "| {}/{} | {} | {} | {} |",
+            shown(info.network, info.prefix, anon),
+            info.prefix,
+            info.broadcast
+                .map_or("-".to_string(), |b| shown(b, info.prefix, anon)),
+            info.netmask,
+            format_count(info.usable.unwrap_or(0), si)
         );
     }
+}
+
+/// Teaching view: every derived value followed by a sentence showing how it
+/// follows from the address and prefix.
+fn print_explain(net: &IpNetwork, reserve: u8, si: bool) {
+    let info = describe_reserved(net, reserve);
+    let bits = if net.is_ipv4() { 32 } else { 128 };
+    let host_bits = bits - info.prefix;
+    let host_bits_text = match host_bits {
+        1 => "1 host bit".to_string(),
+        n => format!("{} host bits", n),
+    };
+
+    let mut rows: Vec<(&str, String, String)> = vec![
+        (
+            "Prefix:",
+            format!("/{}", info.prefix),
+            format!(
+                "the first {} of {} bits name the network, leaving {}.",
+                info.prefix, bits, host_bits_text
+            ),
+        ),
+        (
+            "Netmask:",
+            info.netmask.to_string(),
+            format!(
+                "{} one-bits followed by {} zero-bits.",
+                info.prefix, host_bits
+            ),
+        ),
+        (
+            "Network:",
+            info.network.to_string(),
+            format!("{} AND the netmask, clearing every host bit.", net.ip()),
+        ),
+    ];
+    if let Some(bcast) = info.broadcast {
+        rows.push((
+            "Broadcast:",
+            bcast.to_string(),
+            "the network with every host bit set to 1.".to_string(),
+        ));
+    }
+    rows.push((
+        "Addresses:",
+        format_count(info.total, si),
+        format!(
+            "2^{} = 2^({} - {}), one per combination of {}.",
+            host_bits, bits, info.prefix, host_bits_text
+        ),
+    ));
 
+    let note = if info.total == 1 {
+        Some(format!(
+            "a /{} names exactly one host, so it has no separate hosts to list.",
+            bits
+        ))
+    } else {
+        let (usable_why, first_why, last_why) = match net {
+            IpNetwork::V4(_) if info.prefix == 31 => (
+                "both addresses; a point-to-point /31 sets none aside (RFC 3021).".to_string(),
+                "the network address itself, usable on a /31.".to_string(),
+                "the broadcast address itself, usable on a /31.".to_string(),
+            ),
+            IpNetwork::V4(_) if reserve == 0 => (
+                "every address, since none are reserved.".to_string(),
+                "the network address itself.".to_string(),
+                "the broadcast address itself.".to_string(),
+            ),
+            IpNetwork::V4(_) if reserve == 2 => (
+                "the total minus the network and broadcast addresses.".to_string(),
+                "the network address + 1.".to_string(),
+                "the broadcast address - 1.".to_string(),
+            ),
+            IpNetwork::V4(_) => (
+                format!(
+                    "the total minus {} reserved addresses: the first {} and the last.",
+                    reserve,
+                    reserve.saturating_sub(1)
+                ),
+                format!("the network address + {}.", reserve.saturating_sub(1)),
+                "the broadcast address - 1.".to_string(),
+            ),
+            IpNetwork::V6(_) => (
+                "every address; IPv6 has no broadcast to set aside.".to_string(),
+                "the network address itself.".to_string(),
+                "the network with every host bit set to 1.".to_string(),
+            ),
+        };
+        rows.push((
+            "Usable:",
+            format_count(info.usable.unwrap_or(0), si),
+            usable_why,
+        ));
+        if let (Some(first), Some(last)) = (info.first_host, info.last_host) {
+            rows.push(("First Host:", first.to_string(), first_why));
+            rows.push(("Last Host:", last.to_string(), last_why));
+        }
+        None
+    };
+
+    let label_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let value_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
     println!(
-        "  {}  {}",
-        pad_label("Addresses:"),
-        format!("({} usable)", usable).bright_red()
+        "{}",
+        format!("{}/{}:", info.network, info.prefix)
+            .bold()
+            .magenta()
     );
+    for (label, value, why) in &rows {
+        println!(
+            "  {}  {}  {}",
+            format!("{:>label_width$}", label).yellow(),
+            format!("{:<value_width$}", value).cyan(),
+            why
+        );
+    }
+    if let Some(note) = note {
+        println!("  {}  {}", " ".repeat(label_width), note.bright_black());
+    }
+}
+
+/// Quote a CSV field only when it contains a delimiter, quote, or newline.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One raw `--field` value; counts are unformatted for scripts.
+fn field_value(net: &IpNetwork, field: Field, reserve: u8) -> String {
+    let info = describe_reserved(net, reserve);
+    let addr = |a: Option<IpAddr>| a.map_or("-".to_string(), |a| a.to_string());
+    match field {
+        Field::Network => info.network.to_string(),
+        Field::Broadcast => addr(info.broadcast),
+        Field::Netmask => info.netmask.to_string(),
+        Field::Wildcard => match net {
+            IpNetwork::V4(v4) => wildcard(v4).to_string(),
+            IpNetwork::V6(v6) => Ipv6Addr::from(!u128::from(v6.mask())).to_string(),
+        },
+        Field::First => addr(info.first_host),
+        Field::Last => addr(info.last_host),
+        Field::Usable => count_text(info.usable.unwrap_or(0)),
+        Field::Total => count_text(info.total),
+    }
+}
+
+/// `--env` assignments, one variable per field of each network.
+fn print_env(name: &str, nets: &[IpNetwork], reserve: u8) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(usage!(
+            "--env {:?} is not a shell variable name; use letters, digits and '_'",
+            name
+        ));
+    }
+    const FIELDS: [(&str, Field); 8] = [
+        ("NETWORK", Field::Network),
+        ("BROADCAST", Field::Broadcast),
+        ("MASK", Field::Netmask),
+        ("WILDCARD", Field::Wildcard),
+        ("FIRST", Field::First),
+        ("LAST", Field::Last),
+        ("USABLE", Field::Usable),
+        ("TOTAL", Field::Total),
+    ];
+    for (i, net) in nets.iter().enumerate() {
+        let var = if nets.len() == 1 {
+            name.to_string()
+        } else {
+            format!("{}_{}", name, i)
+        };
+        println!("{}_PREFIX={}", var, net.prefix());
+        for (suffix, field) in FIELDS {
+            // Absent values (an IPv6 broadcast) are empty rather than `-` so
+            // scripts can test them with `[ -n "$VAR" ]`.
+            let value = field_value(net, field, reserve);
+            let value = if value == "-" { "" } else { &value };
+            println!("{}_{}={}", var, suffix, shell_quote(value));
+        }
+    }
+    if nets.len() > 1 {
+        println!("{}_COUNT={}", name, nets.len());
+    }
+    Ok(())
+}
+
+/// Single-quote a value unless it is made only of characters the shell
+/// treats literally; addresses always are, but labels may not be.
+fn shell_quote(value: &str) -> String {
+    let literal = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._:/-".contains(c));
+    if literal {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// A parsed `--format` template: literal text interleaved with values.
+enum Piece {
+    Text(String),
+    Field(Field),
+    Prefix,
+    HexNetwork,
+}
+
+/// Split a `--format` template into pieces, rejecting unknown or unclosed tokens
+/// up front so a typo fails before any output is written.
+fn parse_template(template: &str) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(usage!("unclosed '{{{}' in --format template", name)),
+                    }
+                }
+                let piece = match name.as_str() {
+                    "network" => Piece::Field(Field::Network),
+                    "broadcast" => Piece::Field(Field::Broadcast),
+                    "netmask" => Piece::Field(Field::Netmask),
+                    "first" => Piece::Field(Field::First),
+                    "last" => Piece::Field(Field::Last),
+                    "usable" => Piece::Field(Field::Usable),
+                    "total" => Piece::Field(Field::Total),
+                    "prefix" => Piece::Prefix,
+                    "hex_network" => Piece::HexNetwork,
+                    _ => {
+                        return Err(usage!(
+                            "unknown token '{{{}}}' in --format template; expected one of \
+                             {{network}} {{broadcast}} {{netmask}} {{prefix}} {{first}} {{last}} \
+                             {{usable}} {{total}} {{hex_network}}",
+                            name
+                        ));
+                    }
+                };
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(piece);
+            }
+            '}' => {
+                return Err(usage!(
+                    "unmatched '}}' in --format template; use '}}}}' for a literal brace"
+                ));
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// Fill a parsed `--format` template for one network; values are raw like `--field`.
+fn render_template(pieces: &[Piece], net: &IpNetwork, reserve: u8) -> String {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.clone(),
+            Piece::Field(field) => field_value(net, *field, reserve),
+            Piece::Prefix => net.prefix().to_string(),
+            Piece::HexNetwork => hex(net.network()),
+        })
+        .collect()
+}
+
+/// Compact `key=value` summary; the field order is part of the CLI contract.
+fn oneline(info: &NetworkInfo, anon: bool, si: bool) -> String {
+    let network = shown(info.network, info.prefix, anon);
+    format!(
+        "{}/{} net={} bcast={} mask={} hosts={}",
+        network,
+        info.prefix,
+        network,
+        info.broadcast
+            .map_or("-".to_string(), |b| shown(b, info.prefix, anon)),
+        info.netmask,
+        format_count(info.usable.unwrap_or(0), si)
+    )
+}
+
+/// An address as printed, or with its host octets masked under `--anonymize`.
+fn shown(addr: IpAddr, prefix: u8, anon: bool) -> String {
+    if anon {
+        anonymize(addr, prefix)
+    } else {
+        addr.to_string()
+    }
+}
+
+/// Pretty-print one network block; see `describe_string`.
+fn print_network(net: &IpNetwork, opts: &RenderOpts) {
+    print!("{}", describe_string(net, opts));
+}
+
+/// Usable addresses across already-aggregated blocks.
+fn usable_total(merged: &[Ipv4Network], reserve: u8) -> u128 {
+    merged
+        .iter()
+        .map(|net| usable_addresses(&IpNetwork::V4(*net), reserve))
+        .sum()
+}
+
+/// Footer for multi-network runs; overlapping IPv4 blocks are aggregated
+/// first so shared addresses count once.
+fn summary(nets: &[IpNetwork], opts: &RenderOpts) -> String {
+    let v4: Vec<Ipv4Network> = nets
+        .iter()
+        .filter_map(|net| match net {
+            IpNetwork::V4(v4) => Some(*v4),
+            IpNetwork::V6(_) => None,
+        })
+        .collect();
+    let merged = aggregate(&v4);
+    let usable = usable_total(&merged, opts.reserve);
+
+    let label = |s: &str| format!("{:>9}", s).yellow();
+    let mut lines = vec![
+        "Summary:".bold().magenta().to_string(),
+        format!("  {}  {}", label("Networks:"), nets.len()),
+        format!(
+            "  {}  {}",
+            label("Usable:"),
+            format!("{} (overlaps counted once)", format_count(usable, opts.si)).bright_red()
+        ),
+    ];
+    if let (Some(first), Some(last)) = (merged.first(), merged.last()) {
+        let addr =
+            |net: &Ipv4Network, a: Ipv4Addr| shown(IpAddr::V4(a), net.prefix(), opts.anonymize);
+        lines.push(format!(
+            "  {}  {} - {}",
+            label("Span:"),
+            addr(first, first.network()).cyan(),
+            addr(last, last.broadcast()).cyan()
+        ));
+    }
+    if v4.len() < nets.len() {
+        lines.push(format!(
+            "  {}  {}",
+            label("Note:"),
+            "IPv6 networks are counted but not totalled".bright_black()
+        ));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_follows_the_error_kind() {
+        assert_eq!(exit_code(&eyre!("bad input")), ExitCode::Parse);
+        assert_eq!(exit_code(&usage!("bad flag")), ExitCode::Usage);
+        assert_eq!(exit_code(&as_check(eyre!("no fit"))), ExitCode::CheckFailed);
+        let wrapped = Err::<(), _>(usage!("bad flag")).wrap_err("context");
+        assert_eq!(exit_code(&wrapped.unwrap_err()), ExitCode::Usage);
+    }
+
+    #[test]
+    fn flag_value_errors_exit_as_usage() {
+        let net: IpNetwork = "10.0.0.0/24".parse().unwrap();
+        let err = split_all(&[net], 20, false).unwrap_err();
+        assert_eq!(exit_code(&err), ExitCode::Usage);
+    }
+
+    #[test]
+    fn vlsm_overflow_is_a_check_failure() {
+        let base: Ipv4Network = "10.0.0.0/28".parse().unwrap();
+        let err = print_vlsm(&base, &[50], &RenderOpts::default()).unwrap_err();
+        assert_eq!(exit_code(&err), ExitCode::CheckFailed);
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("cidr").chain(args.iter().copied()))
+            .expect("valid test flags")
+    }
+
+    fn parsed(cli: &Cli, spec: &str) -> String {
+        parse_entry(cli, spec).expect("spec parses").net.to_string()
+    }
+
+    #[test]
+    fn global_mask_skips_inline_prefixes() {
+        let cli = cli(&["--mask", "255.255.255.0", "10.0.0.1", "10.0.1.0/16"]);
+        assert_eq!(parsed(&cli, "10.0.0.1"), "10.0.0.1/24");
+        assert_eq!(parsed(&cli, "10.0.1.0/16"), "10.0.1.0/16");
+        assert_eq!(overridden_mask(&cli, "10.0.0.1"), None);
+        assert_eq!(overridden_mask(&cli, "10.0.1.0/16"), Some("--mask"));
+    }
+
+    #[test]
+    fn prefix_and_wildcard_follow_the_same_rule() {
+        let by_prefix = cli(&["--prefix", "24", "10.0.0.1"]);
+        assert_eq!(parsed(&by_prefix, "10.0.0.1"), "10.0.0.1/24");
+        assert_eq!(parsed(&by_prefix, "10.0.1.0/16"), "10.0.1.0/16");
+        assert_eq!(overridden_mask(&by_prefix, "10.0.1.0/16"), Some("--prefix"));
+
+        let by_wildcard = cli(&["--wildcard", "0.0.0.255", "10.0.0.1"]);
+        assert_eq!(parsed(&by_wildcard, "10.0.1.0/16"), "10.0.1.0/16");
+        assert_eq!(
+            overridden_mask(&by_wildcard, "10.0.1.0/16"),
+            Some("--wildcard")
+        );
+    }
+
+    #[test]
+    fn prefer_mask_drops_inline_prefixes() {
+        let cli = cli(&["--prefer-mask", "-m", "255.255.255.0", "10.0.1.0/16"]);
+        assert_eq!(parsed(&cli, "10.0.1.0/16"), "10.0.1.0/24");
+        assert_eq!(overridden_mask(&cli, "10.0.1.0/16"), None);
+    }
+
+    #[test]
+    fn oneline_counts_follow_si() {
+        let info = describe_reserved(&"10.0.0.0/8".parse().unwrap(), 2);
+        assert!(oneline(&info, false, false).ends_with("hosts=16,777,214"));
+        assert!(oneline(&info, false, true).ends_with("hosts=16.7M"));
+    }
+
+    #[test]
+    fn yaml_quotes_addresses_in_serialized_order() {
+        let info = describe_reserved(&"10.0.0.0/31".parse().unwrap(), 2);
+        let json = serde_json::to_string(&info).unwrap();
+        let fields = yaml_fields(&info);
+        let at: Vec<usize> = fields
+            .iter()
+            .map(|(key, _)| json.find(&format!("\"{}\":", key)).expect("serialized key"))
+            .collect();
+        assert!(at.is_sorted());
+        assert_eq!(json.matches("\":").count(), fields.len());
+        assert_eq!(fields[0].1, "'10.0.0.0'");
+        assert_eq!(fields[3].1, "31");
+        assert_eq!(fields[6].1, "2");
+        assert_eq!(fields[9].1, "167772161");
+    }
+
+    /// Apply `config` to a command line the way `try_main` does.
+    fn configured(args: &[&str], config: Config) -> Cli {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        config.apply(&mut cli, &matches);
+        cli
+    }
+
+    #[test]
+    fn config_format_respects_flag_conflicts() {
+        let json = || Config {
+            format: Some(ConfigFormat::Json),
+            ..Config::default()
+        };
+        assert!(configured(&["cidr", "10.0.3.7/16"], json()).json);
+        assert!(!configured(&["cidr", "--anonymize", "10.0.3.7/16"], json()).json);
+        assert!(!configured(&["cidr", "--summary", "10.0.3.7/16"], json()).json);
+    }
+
+    #[test]
+    fn anonymize_hides_every_original_octet() {
+        colored::control::set_override(false);
+        let opts = RenderOpts {
+            anonymize: true,
+            ..RenderOpts::default()
+        };
+        let nets: Vec<IpNetwork> = vec![
+            "10.77.3.7/16".parse().unwrap(),
+            "10.78.200.9/16".parse().unwrap(),
+        ];
+        let out = grouped(&nets, 2, &opts) + &summary(&nets, &opts);
+        // Only the /16 network octets may survive; every host octet is an x.
+        let words = out.split(|c: char| !c.is_ascii_alphanumeric() && c != '.');
+        for addr in words.filter(|word| word.matches('.').count() == 3) {
+            assert!(addr.ends_with(".x.x"), "{} leaks in {}", addr, out);
+        }
+        let entries = resolve_spec(&cli(&["--anonymize"]), "localhost/16").unwrap();
+        assert_eq!(entries[0].label.as_deref(), Some("localhost (127.0.x.x)"));
+        for flag in [&["--hosts"][..], &["--pick", "1"], &["--random", "1"]] {
+            let args = ["cidr", "--anonymize", "10.0.0.0/24"].iter().chain(flag);
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+    }
+
+    #[test]
+    fn geofeed_rejects_misaligned_prefixes() {
+        let aligned: Vec<IpNetwork> = vec!["192.0.2.0/24".parse().unwrap()];
+        assert_eq!(geofeed_rows(&aligned).unwrap(), ["192.0.2.0/24,,,,"]);
+        let misaligned: Vec<IpNetwork> = vec!["192.0.2.7/24".parse().unwrap()];
+        let err = geofeed_rows(&misaligned).unwrap_err();
+        assert_eq!(exit_code(&err), ExitCode::CheckFailed);
+        assert!(err.to_string().contains("192.0.2.7/24"));
+    }
 }
@@ -0,0 +1,640 @@
+//! Set operations over IPv4 networks.
+
+use crate::{parse_network, split_comment};
+use eyre::{Result, WrapErr, eyre};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::BufRead;
+use std::net::Ipv4Addr;
+
+/// Merge contiguous and overlapping networks into the smallest set of blocks
+/// covering exactly the same addresses, in ascending order.
+pub fn aggregate(nets: &[Ipv4Network]) -> Vec<Ipv4Network> {
+    merge_ranges(nets.iter().map(span).collect())
+        .into_iter()
+        .flat_map(|(lo, hi)| range_blocks(lo, hi))
+        .collect()
+}
+
+/// `aggregate` over a stream of newline-separated networks, such as a route
+/// dump. Only each network's address span is kept while reading, so memory
+/// stays proportional to the prefix count; the merge is a sort and a single
+/// sweep, O(n log n). Blank lines and `#` comments are skipped, and a line
+/// may hold several comma- or whitespace-separated networks.
+///
+/// # Errors
+/// Returns an error if the reader fails or a line holds something other
+/// than IPv4 networks, naming the line.
+pub fn aggregate_lines<R: BufRead>(reader: R) -> Result<Vec<Ipv4Network>> {
+    let mut spans = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.wrap_err("Failed to read input")?;
+        let (body, _) = split_comment(&line);
+        for tok in body
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tok| !tok.is_empty())
+        {
+            let net = match parse_network(tok, None).wrap_err_with(|| format!("line {}", i + 1))? {
+                IpNetwork::V4(net) => net,
+                IpNetwork::V6(net) => {
+                    return Err(eyre!("line {}: {} is not an IPv4 network", i + 1, net));
+                }
+            };
+            spans.push(span(&net));
+        }
+    }
+    Ok(merge_ranges(spans)
+        .into_iter()
+        .flat_map(|(lo, hi)| range_blocks(lo, hi))
+        .collect())
+}
+
+/// The destination prefix of an `ip route` line such as
+/// `10.0.0.0/24 via 10.0.0.1 dev eth0`, ignoring everything after it.
+///
+/// A leading route type (`blackhole`, `unreachable`, ...) is skipped and a
+/// bare address is a `/32` host route. Default routes, IPv6 routes and
+/// lines that do not start with a destination give `None`, so a whole
+/// table can be fed through without pre-filtering.
+pub fn route_prefix(line: &str) -> Option<Ipv4Network> {
+    const ROUTE_TYPES: [&str; 10] = [
+        "unicast",
+        "local",
+        "broadcast",
+        "multicast",
+        "throw",
+        "unreachable",
+        "prohibit",
+        "blackhole",
+        "nat",
+        "anycast",
+    ];
+    let mut tokens = line
+        .split_whitespace()
+        .skip_while(|tok| ROUTE_TYPES.contains(tok));
+    match parse_network(tokens.next()?, None).ok()? {
+        IpNetwork::V4(net) => Some(net),
+        IpNetwork::V6(_) => None,
+    }
+}
+
+/// Smallest list of CIDR blocks exactly covering the inclusive range `start..=end`.
+///
+/// # Errors
+/// Returns an error if `start` is greater than `end`.
+pub fn range_to_cidrs(start: Ipv4Addr, end: Ipv4Addr) -> Result<Vec<Ipv4Network>> {
+    if start > end {
+        return Err(eyre!("range start {} is after end {}", start, end));
+    }
+    Ok(range_blocks(start.into(), end.into()))
+}
+
+/// Minimal set of blocks covering `base` minus `hole`, in ascending order.
+///
+/// A hole outside `base` leaves it unchanged; a hole covering all of it
+/// leaves nothing.
+pub fn exclude(base: &Ipv4Network, hole: &Ipv4Network) -> Vec<Ipv4Network> {
+    if !overlaps(base, hole) {
+        return vec![*base];
+    }
+    let (lo, hi) = span(base);
+    let (hole_lo, hole_hi) = span(hole);
+    let mut out = Vec::new();
+    if hole_lo > lo {
+        out.extend(range_blocks(lo, hole_lo - 1));
+    }
+    if hole_hi < hi {
+        out.extend(range_blocks(hole_hi + 1, hi));
+    }
+    out
+}
+
+/// Fold `exclude` over every hole, removing each from what remains of `base`.
+pub fn exclude_all(base: &Ipv4Network, holes: &[Ipv4Network]) -> Vec<Ipv4Network> {
+    holes.iter().fold(vec![*base], |remaining, hole| {
+        remaining
+            .iter()
+            .flat_map(|block| exclude(block, hole))
+            .collect()
+    })
+}
+
+/// Allocate one subnet per host requirement from `base`, largest first.
+///
+/// Each request is rounded up to the next power of two after adding the
+/// `reserve` addresses withheld from every block (see `usable_hosts`), so the
+/// block always has room for the hosts asked for; packing largest-first keeps
+/// every block aligned without gaps. Returns `(requested hosts, subnet)` in
+/// allocation order.
+///
+/// # Errors
+/// Returns an error if the requests do not fit inside `base`.
+pub fn vlsm(base: &Ipv4Network, hosts: &[u32], reserve: u8) -> Result<Vec<(u32, Ipv4Network)>> {
+    let mut wanted = hosts.to_vec();
+    wanted.sort_unstable_by(|a, b| b.cmp(a));
+
+    let (lo, hi) = span(base);
+    let mut cursor = u64::from(lo);
+    let mut out = Vec::with_capacity(wanted.len());
+    for want in wanted {
+        let size = (u64::from(want) + u64::from(reserve)).next_power_of_two();
+        if cursor + size > u64::from(hi) + 1 {
+            return Err(eyre!(
+                "{} hosts (a block of {}) does not fit in the remaining space of {}",
+                want,
+                size,
+                base
+            ));
+        }
+        let prefix = (32 - size.trailing_zeros()) as u8;
+        let net = Ipv4Network::new(Ipv4Addr::from(cursor as u32), prefix)
+            .wrap_err("Failed to build VLSM subnet")?;
+        out.push((want, net));
+        cursor += size;
+    }
+    Ok(out)
+}
+
+/// The same-sized block immediately after `net`, or `None` past 255.255.255.255.
+pub fn next_network(net: &Ipv4Network) -> Option<Ipv4Network> {
+    let (_, hi) = span(net);
+    let start = hi.checked_add(1)?;
+    Ipv4Network::new(Ipv4Addr::from(start), net.prefix()).ok()
+}
+
+/// The same-sized block immediately before `net`, or `None` below 0.0.0.0.
+pub fn prev_network(net: &Ipv4Network) -> Option<Ipv4Network> {
+    let (lo, hi) = span(net);
+    let start = lo.checked_sub(hi - lo)?.checked_sub(1)?;
+    Ipv4Network::new(Ipv4Addr::from(start), net.prefix()).ok()
+}
+
+/// The overlapping portion of two blocks: since CIDRs nest, it is the more
+/// specific block whenever they overlap.
+pub fn intersect(a: &Ipv4Network, b: &Ipv4Network) -> Option<Ipv4Network> {
+    if !overlaps(a, b) {
+        return None;
+    }
+    let inner = if a.prefix() >= b.prefix() { a } else { b };
+    Some(aligned(span(inner).0, inner.prefix()))
+}
+
+/// The immediate parent block (one bit shorter), or `None` for `/0`.
+pub fn supernet(net: &Ipv4Network) -> Option<Ipv4Network> {
+    let prefix = net.prefix().checked_sub(1)?;
+    Some(aligned(span(net).0, prefix))
+}
+
+/// Whether two equal-size blocks are the two halves of one parent, i.e.
+/// they merge into their shared `supernet`.
+pub fn are_siblings(a: &Ipv4Network, b: &Ipv4Network) -> bool {
+    a.prefix() == b.prefix() && a.network() != b.network() && supernet(a) == supernet(b)
+}
+
+/// How two IPv4 blocks relate. CIDR blocks never partially overlap, so any
+/// two are equal, nested, or disjoint; disjoint ones are further told apart
+/// by whether they touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    Equal,
+    /// The first block contains the second.
+    Contains,
+    /// The first block lies inside the second.
+    ContainedBy,
+    /// Both halves of this parent block.
+    Siblings(Ipv4Network),
+    /// Touching, but not mergeable into one block.
+    Adjacent,
+    /// Separated by this many addresses.
+    Disjoint {
+        gap: u64,
+    },
+}
+
+/// Classify how `a` relates to `b`.
+pub fn relationship(a: &Ipv4Network, b: &Ipv4Network) -> Relationship {
+    let (a_lo, a_hi) = span(a);
+    let (b_lo, b_hi) = span(b);
+    if (a_lo, a_hi) == (b_lo, b_hi) {
+        return Relationship::Equal;
+    }
+    if contains(a, b) {
+        return Relationship::Contains;
+    }
+    if contains(b, a) {
+        return Relationship::ContainedBy;
+    }
+    if are_siblings(a, b) {
+        let parent = supernet(a).expect("siblings have a parent");
+        return Relationship::Siblings(parent);
+    }
+    let gap = if a_hi < b_lo {
+        u64::from(b_lo) - u64::from(a_hi) - 1
+    } else {
+        u64::from(a_lo) - u64::from(b_hi) - 1
+    };
+    if gap == 0 {
+        Relationship::Adjacent
+    } else {
+        Relationship::Disjoint { gap }
+    }
+}
+
+/// The aligned `/prefix` block containing `addr`.
+///
+/// # Panics
+/// Panics if `prefix` is greater than 32.
+pub fn block_at_prefix(addr: Ipv4Addr, prefix: u8) -> Ipv4Network {
+    assert!(prefix <= 32, "IPv4 prefix /{} is out of range", prefix);
+    aligned(addr.into(), prefix)
+}
+
+/// Group host addresses by the aligned `/prefix` block each falls in, hosts
+/// sorted and deduplicated within their block.
+///
+/// # Panics
+/// Panics if `prefix` is greater than 32.
+pub fn bucket(hosts: &[Ipv4Addr], prefix: u8) -> BTreeMap<Ipv4Network, Vec<Ipv4Addr>> {
+    let mut buckets: BTreeMap<Ipv4Network, Vec<Ipv4Addr>> = BTreeMap::new();
+    for &host in hosts {
+        buckets
+            .entry(block_at_prefix(host, prefix))
+            .or_default()
+            .push(host);
+    }
+    for members in buckets.values_mut() {
+        members.sort();
+        members.dedup();
+    }
+    buckets
+}
+
+/// The smallest single CIDR block containing both networks.
+pub fn smallest_covering(a: &Ipv4Network, b: &Ipv4Network) -> Ipv4Network {
+    let (a_lo, _) = span(a);
+    let (b_lo, _) = span(b);
+    let common = (a_lo ^ b_lo).leading_zeros() as u8;
+    aligned(a_lo, common.min(a.prefix()).min(b.prefix()))
+}
+
+/// Lazily yield the usable host addresses: everything between network and
+/// broadcast, both ends of a `/31`, or the single address of a `/32`.
+pub fn hosts(net: &Ipv4Network) -> impl DoubleEndedIterator<Item = Ipv4Addr> + use<> {
+    usable_hosts(net, 2)
+}
+
+/// `hosts` with `reserve` addresses withheld as `host_range` withholds them;
+/// empty when the reservation swallows the block.
+pub fn usable_hosts(
+    net: &Ipv4Network,
+    reserve: u8,
+) -> impl DoubleEndedIterator<Item = Ipv4Addr> + use<> {
+    let (first, last) = reserved_span(net, reserve).unwrap_or((1, 0));
+    (first..=last).map(Ipv4Addr::from)
+}
+
+/// First and last usable host once `reserve` addresses are withheld the
+/// way `describe_reserved` withholds them (the first `reserve - 1` and the
+/// last), or `None` when the reservation swallows the block. `/31` and `/32`
+/// ignore `reserve`, as `hosts` does.
+pub fn host_range(net: &Ipv4Network, reserve: u8) -> Option<(Ipv4Addr, Ipv4Addr)> {
+    reserved_span(net, reserve).map(|(first, last)| (Ipv4Addr::from(first), Ipv4Addr::from(last)))
+}
+
+/// The `i`th usable host of `host_range`, counting from 1, so `10.0.0.0/24`
+/// picks `10.0.0.10` for 10 under the classic reserve of 2 and `10.0.0.13`
+/// under AWS's 5; negative indices count back from the last host (-1).
+/// `None` for 0, an index past either end, or a block with no usable hosts.
+pub fn nth_host(net: &Ipv4Network, i: i64, reserve: u8) -> Option<Ipv4Addr> {
+    let (first, last) = reserved_span(net, reserve)?;
+    let steps = u32::try_from(i.unsigned_abs().checked_sub(1)?).ok()?;
+    let host = if i > 0 {
+        first.checked_add(steps).filter(|&h| h <= last)
+    } else {
+        last.checked_sub(steps).filter(|&h| h >= first)
+    };
+    host.map(Ipv4Addr::from)
+}
+
+/// Where an address sits within its block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostRole {
+    Network,
+    Broadcast,
+    /// The first usable host; also the only address of a `/32`.
+    First,
+    Last,
+    /// A usable host strictly between the first and last.
+    Middle,
+}
+
+impl fmt::Display for HostRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HostRole::Network => "network",
+            HostRole::Broadcast => "broadcast",
+            HostRole::First => "first host",
+            HostRole::Last => "last host",
+            HostRole::Middle => "middle host",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The role of `addr`, which must lie in `net`. Usable hosts follow `hosts`,
+/// so both ends of a `/31` are first and last host rather than network and
+/// broadcast.
+pub fn host_role(addr: Ipv4Addr, net: &Ipv4Network) -> HostRole {
+    debug_assert!(net.contains(addr), "{} lies outside {}", addr, net);
+    let (lo, hi) = span(net);
+    let (first, last) = host_span(net);
+    match u32::from(addr) {
+        a if a == first => HostRole::First,
+        a if a == last => HostRole::Last,
+        a if a == lo => HostRole::Network,
+        a if a == hi => HostRole::Broadcast,
+        _ => HostRole::Middle,
+    }
+}
+
+/// First and last usable host as integers; see `hosts`.
+fn host_span(net: &Ipv4Network) -> (u32, u32) {
+    reserved_span(net, 2).expect("the classic reserve leaves every block a host")
+}
+
+/// `host_range` as integers.
+fn reserved_span(net: &Ipv4Network, reserve: u8) -> Option<(u32, u32)> {
+    let (lo, hi) = span(net);
+    if net.prefix() >= 31 {
+        return Some((lo, hi));
+    }
+    let reserve = u32::from(reserve);
+    if reserve > hi - lo {
+        return None;
+    }
+    Some((lo + reserve.saturating_sub(1), hi - reserve.min(1)))
+}
+
+/// Which usable host a subnet's gateway conventionally takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gateway {
+    /// The first usable host, e.g. `.1` in a `/24`.
+    First,
+    /// The last usable host, e.g. `.254` in a `/24`.
+    Last,
+}
+
+/// The conventional gateway address, chosen from the usable hosts as
+/// `hosts` defines them: a `/31` picks one of its two ends, and a `/32`
+/// can only name its single address.
+pub fn gateway(net: &Ipv4Network, which: Gateway) -> Ipv4Addr {
+    let mut usable = hosts(net);
+    let host = match which {
+        Gateway::First => usable.next(),
+        Gateway::Last => usable.next_back(),
+    };
+    host.expect("every block has at least one usable host")
+}
+
+/// Sort ascending by network address, then by prefix length.
+pub fn sort_networks(nets: &mut [Ipv4Network]) {
+    nets.sort_by_key(key);
+}
+
+/// Whether `inner` lies entirely within `outer`; a `/32` tests host membership.
+pub fn contains(outer: &Ipv4Network, inner: &Ipv4Network) -> bool {
+    outer.network() <= inner.network() && inner.broadcast() <= outer.broadcast()
+}
+
+/// The most specific candidate containing `target`, as a routing table
+/// lookup picks its route; the earliest wins among equal prefixes.
+pub fn longest_match(target: Ipv4Addr, candidates: &[Ipv4Network]) -> Option<Ipv4Network> {
+    candidates.iter().filter(|net| net.contains(target)).fold(
+        None,
+        |best: Option<Ipv4Network>, net| match best {
+            Some(best) if best.prefix() >= net.prefix() => Some(best),
+            _ => Some(*net),
+        },
+    )
+}
+
+/// Whether the two blocks share at least one address.
+pub fn overlaps(a: &Ipv4Network, b: &Ipv4Network) -> bool {
+    a.network() <= b.broadcast() && b.network() <= a.broadcast()
+}
+
+/// Networks present in only one of two plans, or in both.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlanDiff {
+    pub only_a: Vec<Ipv4Network>,
+    pub only_b: Vec<Ipv4Network>,
+    pub both: Vec<Ipv4Network>,
+}
+
+/// Compare two plans as sets of normalized networks, so `10.0.0.5/24` and
+/// `10.0.0.9/24` are the same entry. Each list comes back sorted and free of
+/// duplicates.
+pub fn diff(a: &[Ipv4Network], b: &[Ipv4Network]) -> PlanDiff {
+    let normalize = |nets: &[Ipv4Network]| {
+        let mut out: Vec<Ipv4Network> = nets
+            .iter()
+            .map(|net| aligned(span(net).0, net.prefix()))
+            .collect();
+        sort_networks(&mut out);
+        out.dedup();
+        out
+    };
+    let (a, b) = (normalize(a), normalize(b));
+
+    let mut result = PlanDiff::default();
+    for net in &a {
+        if b.binary_search_by_key(&(net.network(), net.prefix()), key)
+            .is_ok()
+        {
+            result.both.push(*net);
+        } else {
+            result.only_a.push(*net);
+        }
+    }
+    result.only_b = b
+        .into_iter()
+        .filter(|net| {
+            a.binary_search_by_key(&(net.network(), net.prefix()), key)
+                .is_err()
+        })
+        .collect();
+    result
+}
+
+/// Sort key shared by `sort_networks` and the plan lookups in `diff`.
+fn key(net: &Ipv4Network) -> (Ipv4Addr, u8) {
+    (net.network(), net.prefix())
+}
+
+/// Index pairs `(i, j)`, `i < j`, of every two inputs that overlap, sorted.
+///
+/// CIDR blocks are either nested or disjoint, so after sorting by start
+/// address a stack of still-open blocks holds exactly the blocks containing
+/// the current one, giving O(n log n + k) for k overlapping pairs.
+pub fn overlapping_pairs(nets: &[Ipv4Network]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..nets.len()).collect();
+    order.sort_unstable_by_key(|&i| {
+        let (lo, hi) = span(&nets[i]);
+        (lo, std::cmp::Reverse(hi))
+    });
+
+    let mut open: Vec<usize> = Vec::new();
+    let mut pairs = Vec::new();
+    for i in order {
+        let (lo, _) = span(&nets[i]);
+        while open.last().is_some_and(|&top| span(&nets[top]).1 < lo) {
+            open.pop();
+        }
+        pairs.extend(open.iter().map(|&outer| (outer.min(i), outer.max(i))));
+        open.push(i);
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// The `/prefix` block containing `addr`, with host bits cleared.
+fn aligned(addr: u32, prefix: u8) -> Ipv4Network {
+    let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+    Ipv4Network::new(Ipv4Addr::from(addr & mask), prefix).expect("prefix is within 0..=32")
+}
+
+/// Inclusive `(first, last)` integer bounds of a network.
+fn span(net: &Ipv4Network) -> (u32, u32) {
+    (u32::from(net.network()), u32::from(net.broadcast()))
+}
+
+/// Sort inclusive ranges and coalesce any that overlap or touch.
+fn merge_ranges(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+    let mut out: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (lo, hi) in ranges {
+        match out.last_mut() {
+            Some(last) if u64::from(lo) <= u64::from(last.1) + 1 => last.1 = last.1.max(hi),
+            _ => out.push((lo, hi)),
+        }
+    }
+    out
+}
+
+/// Minimal list of aligned CIDR blocks exactly covering `lo..=hi`.
+fn range_blocks(lo: u32, hi: u32) -> Vec<Ipv4Network> {
+    let mut out = Vec::new();
+    let mut cur = u64::from(lo);
+    let end = u64::from(hi);
+    while cur <= end {
+        let align = if cur == 0 { 32 } else { cur.trailing_zeros() };
+        // floor(log2(remaining length))
+        let fit = 63 - (end - cur + 1).leading_zeros();
+        let bits = align.min(fit);
+        out.push(
+            Ipv4Network::new(Ipv4Addr::from(cur as u32), (32 - bits) as u8)
+                .expect("block prefix is within 0..=32"),
+        );
+        cur += 1u64 << bits;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(spec: &str) -> Ipv4Network {
+        spec.parse().expect("valid test network")
+    }
+
+    fn addr(text: &str) -> Ipv4Addr {
+        text.parse().expect("valid test address")
+    }
+
+    #[test]
+    fn nth_host_counts_from_either_end() {
+        let net = v4("10.0.0.0/24");
+        assert_eq!(nth_host(&net, 10, 2), Some(addr("10.0.0.10")));
+        assert_eq!(nth_host(&net, -1, 2), Some(addr("10.0.0.254")));
+        assert_eq!(nth_host(&net, 0, 2), None);
+        assert_eq!(nth_host(&net, 255, 2), None);
+        assert_eq!(nth_host(&net, -255, 2), None);
+    }
+
+    #[test]
+    fn nth_host_skips_reserved_addresses() {
+        let net = v4("10.0.0.0/24");
+        assert_eq!(nth_host(&net, 1, 5), Some(addr("10.0.0.4")));
+        assert_eq!(nth_host(&net, -1, 5), Some(addr("10.0.0.254")));
+        assert_eq!(nth_host(&v4("10.0.0.0/29"), 1, 10), None);
+        assert_eq!(nth_host(&v4("10.0.0.0/31"), 2, 5), Some(addr("10.0.0.1")));
+    }
+
+    #[test]
+    fn usable_hosts_withholds_the_reserve() {
+        let aws: Vec<_> = usable_hosts(&v4("10.0.0.0/29"), 5).collect();
+        assert_eq!(aws, [addr("10.0.0.4"), addr("10.0.0.5"), addr("10.0.0.6")]);
+        assert_eq!(usable_hosts(&v4("10.0.0.0/29"), 10).count(), 0);
+        assert_eq!(usable_hosts(&v4("10.0.0.9/32"), 5).count(), 1);
+    }
+
+    fn cidrs(start: &str, end: &str) -> Vec<String> {
+        range_to_cidrs(addr(start), addr(end))
+            .expect("valid range")
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn range_to_cidrs_aligned() {
+        assert_eq!(cidrs("10.0.0.0", "10.0.0.255"), ["10.0.0.0/24"]);
+        assert_eq!(cidrs("0.0.0.0", "255.255.255.255"), ["0.0.0.0/0"]);
+        assert_eq!(cidrs("10.0.0.7", "10.0.0.7"), ["10.0.0.7/32"]);
+    }
+
+    #[test]
+    fn range_to_cidrs_many_blocks() {
+        assert_eq!(
+            cidrs("10.0.0.5", "10.0.0.20"),
+            [
+                "10.0.0.5/32",
+                "10.0.0.6/31",
+                "10.0.0.8/29",
+                "10.0.0.16/30",
+                "10.0.0.20/32"
+            ]
+        );
+        assert_eq!(cidrs("0.0.0.1", "255.255.255.254").len(), 62);
+    }
+
+    #[test]
+    fn range_to_cidrs_rejects_reversed() {
+        assert!(range_to_cidrs(addr("10.0.0.2"), addr("10.0.0.1")).is_err());
+    }
+
+    /// 131,072 reversed `/25`s: a quadratic merge would take minutes here.
+    #[test]
+    fn aggregate_lines_large_input() {
+        let mut text = String::new();
+        for i in (0..1u32 << 17).rev() {
+            let net = Ipv4Network::new(Ipv4Addr::from((10 << 24) | (i << 7)), 25).unwrap();
+            text.push_str(&format!("{}\n", net));
+        }
+        let merged = aggregate_lines(text.as_bytes()).unwrap();
+        assert_eq!(merged, [v4("10.0.0.0/8")]);
+    }
+
+    #[test]
+    fn vlsm_sizes_blocks_for_the_reserve() {
+        let base: Ipv4Network = "10.0.0.0/16".parse().unwrap();
+        let classic = vlsm(&base, &[253], 2).unwrap();
+        assert_eq!(classic[0].1.prefix(), 24);
+        // AWS withholds five addresses, so 253 hosts no longer fit a /24.
+        let cloud = vlsm(&base, &[253], 5).unwrap();
+        assert_eq!(cloud[0].1.prefix(), 23);
+        assert!(usable_hosts(&cloud[0].1, 5).count() >= 253);
+    }
+}
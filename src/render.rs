@@ -0,0 +1,542 @@
+//! The binary's human-readable rendering, as strings so callers can print,
+//! compare or snapshot it.
+
+use crate::{
+    Gateway, IpClass, borrowed_bits, classify, describe, describe_reserved, gateway, ip_class,
+    mixed_scope, octet_boundary, sixtofour_prefix, special_use, subnets_64, user_label, v4_mapped,
+    wildcard,
+};
+use colored::*;
+use ipnetwork::{IpNetwork, Ipv4Network};
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Address counts above this are printed in scientific shorthand (e.g. `1.8e19`).
+const SCI_THRESHOLD: u128 = u64::MAX as u128;
+
+/// Optional rows and styling for `describe_string`.
+#[derive(Debug, Clone)]
+pub struct RenderOpts {
+    /// Add binary rows for the address and mask with the prefix boundary marked
+    pub binary: bool,
+    /// Add the legacy classful interpretation
+    pub class: bool,
+    /// Show the address as typed alongside the computed network
+    pub host: bool,
+    /// Add the IPv4-mapped IPv6 address and 6to4 prefix of the address
+    pub v6map: bool,
+    /// Add the network and broadcast addresses as unsigned integers
+    pub int: bool,
+    /// Add a row per octet showing its network/host split
+    pub octets: bool,
+    /// IPv4 addresses withheld from the usable hosts (see `describe_reserved`)
+    pub reserve: u8,
+    /// Label this usable host as the gateway
+    pub gateway: Option<Gateway>,
+    /// Print counts with metric suffixes; see `format_count`
+    pub si: bool,
+    /// User-named blocks from `--labels`; the best match shows as a row
+    pub labels: Vec<(Ipv4Network, String)>,
+    /// Mask host octets with `x` and drop the hex column and every row that
+    /// would reveal the full address; see `anonymize`
+    pub anonymize: bool,
+    /// Notation for the Netmask row
+    pub mask_style: MaskStyle,
+}
+
+/// How the Netmask row writes the mask, to match a vendor's config syntax.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaskStyle {
+    /// `255.255.255.0`, with the usual hex column
+    #[default]
+    Dotted,
+    /// `0xffffff00`
+    Hex,
+    /// `/24`
+    Prefix,
+}
+
+impl Default for RenderOpts {
+    /// No optional rows, and the classic network and broadcast reservation.
+    fn default() -> Self {
+        RenderOpts {
+            binary: false,
+            class: false,
+            host: false,
+            v6map: false,
+            int: false,
+            octets: false,
+            reserve: 2,
+            gateway: None,
+            si: false,
+            labels: Vec::new(),
+            anonymize: false,
+            mask_style: MaskStyle::Dotted,
+        }
+    }
+}
+
+/// The pretty block the `cidr` binary prints for a network: right-justified,
+/// colored labels (computed width) down to the usable-address count, one
+/// line each, newline-terminated. Colors follow `colored::control`, so
+/// `set_override(false)` yields plain text suitable for golden files.
+pub fn describe_string(net: &IpNetwork, opts: &RenderOpts) -> String {
+    let info = describe_reserved(net, opts.reserve);
+    let mut lines = Vec::new();
+    let mut labels = vec![
+        "Scope:",
+        "Special-Use:",
+        "Label:",
+        "Class:",
+        "Borrowed:",
+        "Network:",
+        "Host:",
+        "Netmask:",
+        "Integer:",
+        "Wildcard:",
+        "Binary:",
+        "Mask Bits:",
+        "First Host:",
+        "Last Host:",
+        "Gateway:",
+        "Addresses:",
+        "/64 Subnets:",
+        "Note:",
+    ];
+    if opts.octets {
+        labels.extend(["Octet 1:", "Octet 2:", "Octet 3:", "Octet 4:"]);
+    }
+    if opts.v6map {
+        labels.extend(["IPv4-Mapped:", "6to4 Prefix:"]);
+    }
+    if info.broadcast.is_some() {
+        labels.push("Broadcast:");
+    }
+    if info.total == 1 {
+        labels = vec!["1 Address Total:"];
+    }
+
+    let label_width = labels
+        .iter()
+        .map(|s| s.len())
+        .max()
+        .expect("at least one label");
+
+    let pad_label = |s: &str| format!("{:>width$}", s, width = label_width).yellow();
+    let row = |label: &str, addr: IpAddr| {
+        if opts.anonymize {
+            return format!(
+                "  {}  {}",
+                pad_label(label),
+                anonymize(addr, info.prefix).cyan()
+            );
+        }
+        format!(
+            "  {}  {}  {:<16}",
+            pad_label(label),
+            hex(addr).bright_black(),
+            addr.to_string().cyan()
+        )
+    };
+    let header = if opts.anonymize {
+        anonymize(info.network, info.prefix)
+    } else {
+        info.network.to_string()
+    };
+
+    lines.push(format!(
+        "{}",
+        format!("{}/{}:", header, info.prefix).bold().magenta()
+    ));
+
+    lines.push(row("Network:", info.network));
+    if opts.host {
+        lines.push(row("Host:", net.ip()));
+    }
+    if let Some(bcast) = info.broadcast {
+        lines.push(row("Broadcast:", bcast));
+    }
+    // Masks are structure, not addressing: anonymizing only drops their hex.
+    let mask_row = |label: &str, mask: IpAddr| {
+        if opts.anonymize {
+            format!("  {}  {}", pad_label(label), mask.to_string().cyan())
+        } else {
+            row(label, mask)
+        }
+    };
+    lines.push(match opts.mask_style {
+        MaskStyle::Dotted => mask_row("Netmask:", info.netmask),
+        MaskStyle::Hex => format!("  {}  {}", pad_label("Netmask:"), hex(info.netmask).cyan()),
+        MaskStyle::Prefix => format!(
+            "  {}  {}",
+            pad_label("Netmask:"),
+            format!("/{}", info.prefix).cyan()
+        ),
+    });
+    if opts.int && !opts.anonymize {
+        let ints = match info.broadcast_int {
+            Some(bcast) => format!("{} - {}", info.network_int, bcast),
+            None => info.network_int.to_string(),
+        };
+        lines.push(format!("  {}  {}", pad_label("Integer:"), ints.cyan()));
+    }
+    if let IpNetwork::V4(v4) = net {
+        if info.total > 1 {
+            lines.push(mask_row("Wildcard:", IpAddr::V4(wildcard(v4))));
+        }
+        let mixed = if mixed_scope(v4) { " (mixed)" } else { "" };
+        lines.push(format!(
+            "  {}  {}",
+            pad_label("Scope:"),
+            format!("{}{}", classify(v4), mixed).green()
+        ));
+        if let Some(name) = special_use(v4) {
+            lines.push(format!("  {}  {}", pad_label("Special-Use:"), name.green()));
+        }
+        if let Some(name) = user_label(v4, &opts.labels) {
+            lines.push(format!(
+                "  {}  {}",
+                pad_label("Label:"),
+                name.green().bold()
+            ));
+        }
+        if opts.class {
+            lines.push(format!(
+                "  {}  {}",
+                pad_label("Class:"),
+                class_summary(v4).green()
+            ));
+            if let Some(borrowed) = borrowed_summary(v4, opts.si) {
+                lines.push(format!(
+                    "  {}  {}",
+                    pad_label("Borrowed:"),
+                    borrowed.green()
+                ));
+            }
+        }
+        if opts.binary && !opts.anonymize {
+            let bits_row = |label: &str, addr: Ipv4Addr| {
+                format!(
+                    "  {}  {}",
+                    pad_label(label),
+                    binary(addr, v4.prefix()).bright_black()
+                )
+            };
+            lines.push(bits_row("Binary:", v4.network()));
+            lines.push(bits_row("Mask Bits:", v4.mask()));
+        }
+        if opts.octets && !opts.anonymize {
+            let (boundary, net_bits) = octet_boundary(v4.prefix());
+            for (i, octet) in v4.ip().octets().into_iter().enumerate() {
+                let bits = format!("{:08b}", octet);
+                let (bits, split) = match i.cmp(&boundary) {
+                    Ordering::Less => (bits, "network".to_string()),
+                    Ordering::Greater => (bits, "host".to_string()),
+                    Ordering::Equal if net_bits == 0 => (bits, "host".to_string()),
+                    Ordering::Equal => {
+                        let (n, h) = bits.split_at(usize::from(net_bits));
+                        let block = 1u16 << (8 - net_bits);
+                        let why = format!(
+                            "{} network bits, {} host bits (block size {})",
+                            net_bits,
+                            8 - net_bits,
+                            block
+                        );
+                        (format!("{}|{}", n, h), why)
+                    }
+                };
+                lines.push(format!(
+                    "  {}  {:>3}  {:<9}  {}",
+                    pad_label(&format!("Octet {}:", i + 1)),
+                    octet.to_string().cyan(),
+                    bits.bright_black(),
+                    split.green()
+                ));
+            }
+        }
+        if opts.v6map && !opts.anonymize {
+            let ip = v4.ip();
+            lines.push(format!(
+                "  {}  {}",
+                pad_label("IPv4-Mapped:"),
+                v4_mapped(ip).to_string().cyan()
+            ));
+            lines.push(format!(
+                "  {}  {}",
+                pad_label("6to4 Prefix:"),
+                sixtofour_prefix(ip).to_string().cyan()
+            ));
+        }
+    }
+
+    if info.total == 1 {
+        lines.push(format!("  {}", pad_label("1 Address Total:")));
+        return finish(lines);
+    }
+
+    if let (Some(first), Some(last)) = (info.first_host, info.last_host) {
+        lines.push(row("First Host:", first));
+        lines.push(row("Last Host:", last));
+    }
+    if let (IpNetwork::V4(v4), Some(which)) = (net, opts.gateway) {
+        let gw = IpAddr::V4(gateway(v4, which));
+        lines.push(if opts.anonymize {
+            format!(
+                "  {}  {}",
+                pad_label("Gateway:"),
+                anonymize(gw, info.prefix).green().bold()
+            )
+        } else {
+            format!(
+                "  {}  {}  {}",
+                pad_label("Gateway:"),
+                hex(gw).bright_black(),
+                gw.to_string().green().bold()
+            )
+        });
+    }
+
+    lines.push(format!(
+        "  {}  {}",
+        pad_label("Addresses:"),
+        format!(
+            "({} usable)",
+            format_count(info.usable.unwrap_or(0), opts.si)
+        )
+        .bright_red()
+    ));
+    if let IpNetwork::V6(v6) = net
+        && let Some(count) = subnets_64(v6)
+    {
+        lines.push(format!(
+            "  {}  {}",
+            pad_label("/64 Subnets:"),
+            format_count(count, opts.si).cyan()
+        ));
+    }
+    if net.is_ipv4() && info.prefix == 31 {
+        lines.push(format!(
+            "  {}  {}",
+            pad_label("Note:"),
+            "point-to-point /31 (RFC 3021)".bright_black()
+        ));
+    }
+    finish(lines)
+}
+
+/// Join rendered lines, newline-terminated like the `println!` calls they replace.
+fn finish(lines: Vec<String>) -> String {
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// Format an address count for people: thousands separators (`16,777,214`),
+/// scientific shorthand past `SCI_THRESHOLD`, or metric suffixes (`16.7M`)
+/// when `si` is set.
+pub fn format_count(n: u128, si: bool) -> String {
+    if si {
+        si_count(n)
+    } else if n > SCI_THRESHOLD {
+        format!("{:.1e}", n as f64)
+    } else {
+        group_thousands(n)
+    }
+}
+
+/// `16777214` as `16,777,214`.
+fn group_thousands(n: u128) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// `16777214` as `16.7M`, truncating to one decimal so the figure never
+/// overstates the count.
+fn si_count(n: u128) -> String {
+    const SUFFIXES: [&str; 10] = ["K", "M", "G", "T", "P", "E", "Z", "Y", "R", "Q"];
+    if n < 1000 {
+        return n.to_string();
+    }
+    let mut scale = 1000u128;
+    let mut suffix = SUFFIXES[0];
+    for next in &SUFFIXES[1..] {
+        if n / scale < 1000 {
+            break;
+        }
+        scale *= 1000;
+        suffix = next;
+    }
+    let tenths = n / (scale / 10);
+    format!("{}.{}{}", tenths / 10, tenths % 10, suffix)
+}
+
+/// Render `addr` with every octet (hextet for IPv6) not wholly inside
+/// `prefix` replaced by `x`, so `10.0.3.7` under /16 reads `10.0.x.x`.
+/// Octets the prefix only partly covers are masked too, since they already
+/// carry host bits. IPv6 is written as full hextets.
+pub fn anonymize(addr: IpAddr, prefix: u8) -> String {
+    let keep = |i: usize, width: usize| (i + 1) * width <= usize::from(prefix);
+    match addr {
+        IpAddr::V4(ip) => ip
+            .octets()
+            .iter()
+            .enumerate()
+            .map(|(i, o)| {
+                if keep(i, 8) {
+                    o.to_string()
+                } else {
+                    "x".into()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("."),
+        IpAddr::V6(ip) => ip
+            .segments()
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                if keep(i, 16) {
+                    format!("{:x}", h)
+                } else {
+                    "x".into()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(":"),
+    }
+}
+
+/// Hex column for an address: 8 digits for IPv4, 32 for IPv6.
+pub fn hex(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(a) => format!("0x{:08x}", u32::from(a)),
+        IpAddr::V6(a) => format!("0x{:032x}", u128::from(a)),
+    }
+}
+
+/// Dotted binary octets with `|` inserted at the prefix boundary,
+/// e.g. `11000000.10101000.00000001.|00000000` for a /24.
+fn binary(addr: Ipv4Addr, prefix: u8) -> String {
+    let bits = u32::from(addr);
+    let mut out = String::with_capacity(36);
+    for i in 0..32u8 {
+        if i > 0 && i % 8 == 0 {
+            out.push('.');
+        }
+        if i == prefix {
+            out.push('|');
+        }
+        out.push(if bits & (1 << (31 - i)) != 0 {
+            '1'
+        } else {
+            '0'
+        });
+    }
+    if prefix == 32 {
+        out.push('|');
+    }
+    out
+}
+
+/// Subnetting-exercise framing of a block against its class default, e.g.
+/// `2 bits from /24: 4 subnets of 62 hosts`.
+fn borrowed_summary(net: &Ipv4Network, si: bool) -> Option<String> {
+    let borrowed = borrowed_bits(net)?;
+    let default = i16::from(net.prefix()) - borrowed;
+    let hosts = match describe(&IpNetwork::V4(*net)).usable {
+        Some(n) => format!("{} hosts", format_count(n, si)),
+        None => "1 address".to_string(),
+    };
+    Some(match borrowed {
+        0 => format!("none; the /{} default mask", default),
+        b if b > 0 => format!(
+            "{} bit{} from /{}: {} subnets of {}",
+            b,
+            if b == 1 { "" } else { "s" },
+            default,
+            1u32 << b,
+            hosts
+        ),
+        b => format!(
+            "none; {} bit{} short of /{}, a supernet of {} /{} networks",
+            -b,
+            if b == -1 { "" } else { "s" },
+            default,
+            1u32 << -b,
+            default
+        ),
+    })
+}
+
+/// Classful reading of a block, e.g. `C (default /24, classless)`.
+fn class_summary(net: &Ipv4Network) -> String {
+    let class = ip_class(net.network());
+    match class.default_prefix() {
+        Some(default) if default == net.prefix() => format!("{} (default /{})", class, default),
+        Some(default) => format!("{} (default /{}, classless)", class, default),
+        None if class == IpClass::D => format!("{} (multicast)", class),
+        None => format!("{} (reserved)", class),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(spec: &str) -> String {
+        colored::control::set_override(false);
+        describe_string(&spec.parse().unwrap(), &RenderOpts::default())
+    }
+
+    #[test]
+    fn describe_string_snapshot_v4() {
+        let expected = concat!(
+            "10.0.0.0/24:\n",
+            "      Network:  0x0a000000  10.0.0.0        \n",
+            "    Broadcast:  0x0a0000ff  10.0.0.255      \n",
+            "      Netmask:  0xffffff00  255.255.255.0   \n",
+            "     Wildcard:  0x000000ff  0.0.0.255       \n",
+            "        Scope:  Private (RFC1918)\n",
+            "  Special-Use:  Private-Use (RFC 1918)\n",
+            "   First Host:  0x0a000001  10.0.0.1        \n",
+            "    Last Host:  0x0a0000fe  10.0.0.254      \n",
+            "    Addresses:  (254 usable)\n",
+        );
+        assert_eq!(plain("10.0.0.0/24"), expected);
+    }
+
+    #[test]
+    fn describe_string_snapshot_single_address() {
+        let expected = concat!(
+            "192.168.1.7/32:\n",
+            "          Network:  0xc0a80107  192.168.1.7     \n",
+            "        Broadcast:  0xc0a80107  192.168.1.7     \n",
+            "          Netmask:  0xffffffff  255.255.255.255 \n",
+            "            Scope:  Private (RFC1918)\n",
+            "      Special-Use:  Private-Use (RFC 1918)\n",
+            "  1 Address Total:\n",
+        );
+        assert_eq!(plain("192.168.1.7/32"), expected);
+    }
+
+    #[test]
+    fn describe_string_snapshot_v6() {
+        let expected = concat!(
+            "2001:db8::/64:\n",
+            "      Network:  0x20010db8000000000000000000000000  2001:db8::      \n",
+            "      Netmask:  0xffffffffffffffff0000000000000000  ffff:ffff:ffff:ffff::\n",
+            "   First Host:  0x20010db8000000000000000000000000  2001:db8::      \n",
+            "    Last Host:  0x20010db800000000ffffffffffffffff  2001:db8::ffff:ffff:ffff:ffff\n",
+            "    Addresses:  (1.8e19 usable)\n",
+            "  /64 Subnets:  1\n",
+        );
+        assert_eq!(plain("2001:db8::/64"), expected);
+    }
+}